@@ -0,0 +1,9 @@
+-- The commit a repository's rustfmt configs were last fetched at. Repositories without a rustfmt
+-- config don't have any rows in `rustfmt_configuration_files`, so this is the only way to tell
+-- whether their configs were fetched. Configs are fetched again whenever it's behind `latest_commit`.
+alter table github_repositories add column if not exists configs_fetched_commit text;
+update github_repositories
+set configs_fetched_commit = configs.latest_commit
+from (select distinct github_graphql_id, latest_commit from rustfmt_configuration_files) as configs
+where github_repositories.github_graphql_id = configs.github_graphql_id
+and github_repositories.latest_commit = configs.latest_commit;
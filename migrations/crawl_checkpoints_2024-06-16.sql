@@ -0,0 +1,7 @@
+-- Where each repository search left off, so interrupted crawls can be resumed.
+create table if not exists crawl_checkpoints (
+    query text primary key not null,
+    cursor text,
+    checkpoint jsonb not null,
+    updated_at timestamp with time zone not null default now()
+);
@@ -0,0 +1,24 @@
+-- Every `add-repo` and `refresh` invocation, so stored data can be traced back to the crawl
+-- that found it.
+create table if not exists crawl_runs (
+    run_id bigint generated always as identity primary key,
+    command text not null,
+    arguments text[] not null,
+    query text,
+    min_stars integer,
+    max_pages integer,
+    started_at timestamp with time zone not null default now(),
+    finished_at timestamp with time zone,
+    repositories_fetched integer not null default 0,
+    repositories_inserted integer not null default 0,
+    repositories_updated integer not null default 0,
+    repositories_rejected integer not null default 0,
+    errors text[] not null default '{}'
+);
+create index if not exists crawl_runs_started_at_index on crawl_runs using btree (started_at);
+
+-- The last crawl that stored each repository.
+alter table github_repositories add column if not exists run_id bigint;
+alter table github_repositories drop constraint if exists fk_run_id;
+alter table github_repositories add constraint fk_run_id foreign key(run_id) references crawl_runs(run_id) on delete set null;
+create index if not exists run_id_index on github_repositories using btree (run_id);
@@ -0,0 +1,3 @@
+-- Denylist entries are kept when they're removed so that there's a record of every opt-out.
+-- Entries with a `removed_at` date no longer apply.
+alter table denylisted_repositories add column if not exists removed_at timestamp with time zone;
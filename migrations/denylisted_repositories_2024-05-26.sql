@@ -0,0 +1,9 @@
+-- Repositories whose maintainers asked us not to include them in the corpus.
+-- Each entry either names a single repository by its GraphQL ID, or matches repositories by
+-- a case insensitive `owner/name` pattern where `*` matches any characters, e.g. `owner/*`.
+create table if not exists denylisted_repositories (
+    entry text primary key not null,
+    is_pattern bool not null,
+    reason text,
+    denylisted_at timestamp with time zone not null default now()
+);
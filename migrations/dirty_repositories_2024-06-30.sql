@@ -0,0 +1,16 @@
+-- `record_last_updated` is only bumped when a repository's code changed, so refreshes are
+-- ordered by when each repository was last checked instead.
+alter table github_repositories add column if not exists record_last_checked timestamp with time zone;
+update github_repositories set record_last_checked = record_last_updated where record_last_checked is null;
+alter table github_repositories alter column record_last_checked set default now();
+alter table github_repositories alter column record_last_checked set not null;
+create index if not exists record_last_checked_index on github_repositories using btree (record_last_checked);
+
+-- Repositories that are new or were pushed to since they were last stored, and still need to be
+-- cloned and formatted.
+create table if not exists dirty_repositories (
+    github_graphql_id text primary key not null,
+    latest_commit text,
+    marked_at timestamp with time zone not null default now(),
+    constraint fk_github_graphql_id foreign key(github_graphql_id) references github_repositories(github_graphql_id) on delete cascade
+);
@@ -0,0 +1,2 @@
+-- Empty repositories don't have a default branch, so they don't have a latest commit either.
+alter table github_repositories alter column latest_commit drop not null;
@@ -0,0 +1,7 @@
+create table if not exists pinned_repositories (
+    github_graphql_id text primary key not null,
+    repo_name text not null,
+    reason text,
+    pinned_at timestamp with time zone not null default now()
+);
+create index if not exists pinned_repo_name_index on pinned_repositories using btree (lower(repo_name));
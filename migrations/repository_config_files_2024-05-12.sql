@@ -0,0 +1,11 @@
+create table if not exists repository_config_files (
+    github_graphql_id text not null,
+    kind text not null check (kind in ('clippy', 'editorconfig', 'pre-commit')),
+    file_path text not null,
+    latest_commit text not null,
+    contents text not null,
+    record_last_updated timestamp with time zone not null default now(),
+    primary key(github_graphql_id, file_path),
+    constraint fk_github_graphql_id foreign key(github_graphql_id) references github_repositories(github_graphql_id) on delete cascade
+);
+create index if not exists repository_config_files_kind_index on repository_config_files using btree (kind);
@@ -0,0 +1,14 @@
+-- Set when a refresh can't find the repository anymore because it was deleted or made private.
+alter table github_repositories add column if not exists gone_at timestamp with time zone;
+create index if not exists gone_at_index on github_repositories using btree (gone_at);
+
+-- Names a repository used to have before it was renamed or transferred to another owner.
+create table if not exists repository_name_history (
+    github_graphql_id text not null,
+    old_repo_name text not null,
+    new_repo_name text not null,
+    changed_at timestamp with time zone not null default now(),
+    constraint fk_github_graphql_id foreign key(github_graphql_id) references github_repositories(github_graphql_id) on delete cascade
+);
+create index if not exists repository_name_history_id_index on repository_name_history using btree (github_graphql_id);
+create index if not exists repository_name_history_old_name_index on repository_name_history using btree (lower(old_repo_name));
@@ -1,3 +1,3 @@
 mod commands;
 
-pub use commands::{Cli, Commands};
+pub use commands::{Cli, Commands, DateWindow, DenylistCommands, PinCommands};
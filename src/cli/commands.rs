@@ -1,4 +1,50 @@
+use crate::{ConfigSource, DenylistEntry};
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use std::str::FromStr;
+use time::format_description::well_known::Iso8601;
+use time::Date;
+
+/// Arguments that only apply when searching GitHub for repositories.
+const SEARCH_ARGS: [&str; 17] = [
+    "stars",
+    "max_stars",
+    "topics",
+    "any_topics",
+    "no_topic",
+    "created",
+    "pushed",
+    "include_forks",
+    "include_templates",
+    "include_archived",
+    "user",
+    "qualifiers",
+    "min_percent_rust",
+    "repo",
+    "limit",
+    "resume",
+    "after",
+];
+
+/// Arguments that don't apply when listing the repositories of a user or organization.
+const OWNER_CONFLICTS: [&str; 16] = [
+    "stars",
+    "max_stars",
+    "topics",
+    "any_topics",
+    "no_topic",
+    "created",
+    "pushed",
+    "include_templates",
+    "include_archived",
+    "user",
+    "qualifiers",
+    "repo",
+    "code_search",
+    "from_file",
+    "resume",
+    "after",
+];
 
 /// Manage the rustfmt user configuration database
 #[derive(Parser)]
@@ -8,6 +54,8 @@ pub struct Cli {
     pub command: Commands,
 }
 
+// Only one command is parsed per run, so the size of the largest variant doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 pub enum Commands {
     /// Add repositories from GitHub into the database
@@ -16,12 +64,53 @@ pub enum Commands {
         /// Limit of how many repositories to fetch on each page
         #[arg(short, long, default_value_t = 100)]
         limit: u16,
-        /// Max number of pages to query
-        #[arg(short, long, default_value_t = 1)]
-        max_pages: u8,
+        /// Max number of pages to query. Defaults to 1, or to every page when listing the
+        /// repositories of an `--owner`
+        #[arg(short, long)]
+        max_pages: Option<u8>,
         /// Filter for repositories that have this number of stars or more
         #[arg(short, long, default_value_t = 50)]
-        stars: u16,
+        stars: u32,
+        /// Filter for repositories that have this number of stars or less
+        #[arg(long)]
+        max_stars: Option<u32>,
+        /// Filter for repositories tagged with this topic. Can be passed multiple times to
+        /// require several topics. Defaults to `rust`
+        #[arg(long = "topic", conflicts_with = "no_topic")]
+        topics: Vec<String>,
+        /// Filter for repositories tagged with at least one of these topics. Can be passed
+        /// multiple times. Replaces the default `rust` topic unless `--topic` is also passed
+        #[arg(long = "any-topic", conflicts_with = "no_topic")]
+        any_topics: Vec<String>,
+        /// Search for repositories regardless of their topics
+        #[arg(long, default_value_t = false)]
+        no_topic: bool,
+        /// Filter for repositories created within a window like `2020-01-01..2020-12-31`.
+        /// Either end of the window can be left open, e.g. `2020-01-01..`
+        #[arg(long)]
+        created: Option<DateWindow>,
+        /// Filter for repositories last pushed to within a window like `2024-01-01..`.
+        /// Either end of the window can be left open
+        #[arg(long)]
+        pushed: Option<DateWindow>,
+        /// Include forks in the search results
+        #[arg(long, default_value_t = false)]
+        include_forks: bool,
+        /// Include template repositories in the search results
+        #[arg(long, default_value_t = false)]
+        include_templates: bool,
+        /// Include archived repositories in the search results
+        #[arg(long, default_value_t = false)]
+        include_archived: bool,
+        /// Filter for repositories owned by this user or organization
+        #[arg(long)]
+        user: Option<String>,
+        /// Extra GitHub search qualifiers added to the search as is, e.g. `license:mit`
+        #[arg(long)]
+        qualifiers: Option<String>,
+        /// Skip repositories where less than this percent of the code is written in Rust
+        #[arg(long)]
+        min_percent_rust: Option<f64>,
         /// The name of repository name to add. Either provide a name like `rustfmt`
         /// or provide the repositroy name with the owner like `rust-lang/rustfmt`
         #[arg(short, long)]
@@ -29,5 +118,208 @@ pub enum Commands {
         /// Print the git repository details instead of storing it in the database
         #[arg(short, long, default_value_t = false)]
         dry_run: bool,
+        /// Don't fetch the rustfmt configs of the repositories
+        #[arg(long, default_value_t = false)]
+        skip_configs: bool,
+        /// Find repositories by searching GitHub for `rustfmt.toml` and `.rustfmt.toml` files
+        /// instead of searching for repositories tagged with the `rust` topic.
+        /// Each page contains up to 100 files.
+        #[arg(long, default_value_t = false, conflicts_with_all = SEARCH_ARGS)]
+        code_search: bool,
+        /// Add the repositories listed in a file instead of searching GitHub.
+        /// Each line can be an `owner/name`, a GitHub URL or a git URL.
+        /// Blank lines and lines starting with `#` are ignored.
+        #[arg(long, conflicts_with_all = SEARCH_ARGS, conflicts_with = "code_search")]
+        from_file: Option<PathBuf>,
+        /// Add every Rust repository owned by this user or organization, regardless of
+        /// their stars or topics. Works with `--include-forks` and `--min-percent-rust`.
+        #[arg(long, conflicts_with_all = OWNER_CONFLICTS)]
+        owner: Option<String>,
+        /// Don't split searches that match more than 1000 repositories into smaller searches.
+        /// GitHub only returns the first 1000 results of a search.
+        #[arg(long, default_value_t = false)]
+        no_partition: bool,
+        /// Stop once this many GitHub GraphQL rate limit points have been spent
+        #[arg(long)]
+        max_points: Option<u64>,
+        /// Retry requests that time out or fail with a server error this many times
+        #[arg(long, default_value_t = 3)]
+        max_retries: u32,
+        /// Continue the search from where the last crawl with the same filters stopped.
+        /// A checkpoint is saved after every page that's stored in the database.
+        #[arg(long, default_value_t = false, conflicts_with_all = ["after", "dry_run"])]
+        resume: bool,
+        /// Continue a `--no-partition` search after the `Next Token` printed by an earlier
+        /// crawl. Partitioned searches don't print a token since it would only cover part of
+        /// the search, continue those with `--resume` instead
+        #[arg(long, requires = "no_partition", conflicts_with = "any_topics")]
+        after: Option<String>,
+    },
+    /// Update the repositories that are already in the database
+    ///
+    /// Repositories are looked up by their GraphQL ID, so they're refreshed even if they no
+    /// longer match the search filters they were found with. Repositories that can't be found
+    /// anymore are marked as gone, and renamed repositories have their old name recorded.
+    #[command(name = "refresh")]
+    Refresh {
+        /// Only refresh this many repositories, starting with the least recently checked
+        #[arg(short, long)]
+        limit: Option<u32>,
+        /// Also refresh repositories that were deleted or made private the last time they were
+        /// refreshed
+        #[arg(long, default_value_t = false)]
+        include_gone: bool,
+        /// Don't fetch the rustfmt configs of the repositories
+        #[arg(long, default_value_t = false)]
+        skip_configs: bool,
+        /// Stop once this many GitHub GraphQL rate limit points have been spent
+        #[arg(long)]
+        max_points: Option<u64>,
+        /// Retry requests that time out or fail with a server error this many times
+        #[arg(long, default_value_t = 3)]
+        max_retries: u32,
+    },
+    /// Manage the repositories that are always part of the corpus
+    ///
+    /// Pinned repositories are refreshed on every `add-repo` crawl, no matter which search
+    /// filters are used. Pinning doesn't protect a repository from anything else: denylisting
+    /// a pinned repository unpins and purges it, and pinned repositories that were deleted or
+    /// made private are marked as gone like any other repository.
+    #[command(name = "pin")]
+    Pin {
+        #[command(subcommand)]
+        command: PinCommands,
+    },
+    /// Manage the repositories that must never be added to the database
+    ///
+    /// Denylisted repositories are skipped by every `add-repo` mode, and any data already
+    /// stored for them is purged when they're added to the denylist.
+    #[command(name = "denylist")]
+    Denylist {
+        #[command(subcommand)]
+        command: DenylistCommands,
+    },
+    /// Show the rustfmt options that differ in effective value between two configs
+    ///
+    /// Each config can be a local rustfmt.toml file, the name of a stored repository like
+    /// `rust-lang/rustfmt`, or the hash of a stored config. Use a `file:`, `repo:` or `hash:`
+    /// prefix if the kind of config is ambiguous.
+    #[command(name = "config-diff")]
+    ConfigDiff {
+        /// The config to compare against
+        left: ConfigSource,
+        /// The config to compare
+        right: ConfigSource,
+    },
+    /// Find the repositories with rustfmt configs most similar to a local rustfmt.toml
+    #[command(name = "similar")]
+    Similar {
+        /// Path to the rustfmt.toml to compare against the stored configs
+        path: PathBuf,
+        /// Number of similar repositories to show
+        #[arg(short, long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Group the stored rustfmt configs into clusters of similar options
+    #[command(name = "presets")]
+    Presets {
+        /// How similar a config needs to be to a cluster to join it, between 0.0 and 1.0
+        #[arg(short, long, default_value_t = 0.6)]
+        min_similarity: f64,
+        /// Hide clusters with fewer configs than this
+        #[arg(short = 's', long, default_value_t = 2)]
+        min_size: usize,
+        /// Number of representative repositories to show for each cluster
+        #[arg(short, long, default_value_t = 3)]
+        representatives: usize,
+    },
+}
+
+impl Commands {
+    /// Does the command need `DATABASE_URL`? Only diffing local config files doesn't.
+    pub fn needs_database(&self) -> bool {
+        match self {
+            Commands::ConfigDiff { left, right } => left.needs_database() || right.needs_database(),
+            _ => true,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum PinCommands {
+    /// Pin repositories and add them to the database
+    Add {
+        /// Repositories to pin, like `rust-lang/rustfmt` or `https://github.com/rust-lang/rust`
+        #[arg(required = true)]
+        repos: Vec<String>,
+        /// Why the repositories need to be in the corpus
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Unpin repositories. Their data is kept in the database
+    Remove {
+        /// Repositories to unpin, like `rust-lang/rustfmt`
+        #[arg(required = true)]
+        repos: Vec<String>,
+    },
+    /// List the pinned repositories
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum DenylistCommands {
+    /// Denylist repositories and purge any data stored for them
+    Add {
+        /// GraphQL IDs of repositories, or `owner/name` patterns where `*` matches any
+        /// characters, e.g. `owner/*`
+        #[arg(required = true)]
+        entries: Vec<DenylistEntry>,
+        /// Why the repositories are denylisted, e.g. a link to the maintainer's request
+        #[arg(long)]
+        reason: Option<String>,
     },
+    /// Remove entries from the denylist
+    Remove {
+        /// Entries to remove, exactly as they were added
+        #[arg(required = true)]
+        entries: Vec<DenylistEntry>,
+    },
+    /// List the denylist entries
+    List,
+}
+
+/// A window of dates like `2020-01-01..2020-12-31`, where either end can be left open.
+#[derive(Debug, Clone, Copy)]
+pub struct DateWindow {
+    pub start: Option<Date>,
+    pub end: Option<Date>,
+}
+
+impl FromStr for DateWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_date = |date: &str| {
+            if date.is_empty() {
+                return Ok(None);
+            }
+            Date::parse(date, &Iso8601::DATE)
+                .map(Some)
+                .map_err(|err| format!("invalid date `{date}`: {err}"))
+        };
+
+        let Some((start, end)) = s.split_once("..") else {
+            return Err(format!("`{s}` should look like `YYYY-MM-DD..YYYY-MM-DD`"));
+        };
+        let window = DateWindow {
+            start: parse_date(start)?,
+            end: parse_date(end)?,
+        };
+        if let (Some(start), Some(end)) = (window.start, window.end) {
+            if start > end {
+                return Err(format!("`{s}` starts after it ends"));
+            }
+        }
+        Ok(window)
+    }
 }
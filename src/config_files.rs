@@ -0,0 +1,195 @@
+use std::fmt::Display;
+
+/// Formatting related config files that we track alongside rustfmt configs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFileKind {
+    /// `clippy.toml` or `.clippy.toml`
+    Clippy,
+    /// `.editorconfig`
+    EditorConfig,
+    /// `.pre-commit-config.yaml`
+    PreCommit,
+}
+
+impl ConfigFileKind {
+    /// Get the [ConfigFileKind] for a file path, if it's a file we track.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let file_name = path.rsplit('/').next().unwrap_or(path);
+        match file_name {
+            "clippy.toml" | ".clippy.toml" => Some(ConfigFileKind::Clippy),
+            ".editorconfig" => Some(ConfigFileKind::EditorConfig),
+            ".pre-commit-config.yaml" => Some(ConfigFileKind::PreCommit),
+            _ => None,
+        }
+    }
+
+    /// The value stored in the `kind` column of the `repository_config_files` table.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConfigFileKind::Clippy => "clippy",
+            ConfigFileKind::EditorConfig => "editorconfig",
+            ConfigFileKind::PreCommit => "pre-commit",
+        }
+    }
+}
+
+impl Display for ConfigFileKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A formatting related config file found in a repository.
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    pub kind: ConfigFileKind,
+    /// Path to the file within the repository
+    pub file_path: String,
+    /// The contents of the file. For `.pre-commit-config.yaml` this only includes the
+    /// rustfmt related hooks.
+    pub contents: String,
+}
+
+impl ConfigFile {
+    /// Create a [ConfigFile] from a file in a repository.
+    ///
+    /// Returns [None] if we don't track the file, or if it's a pre-commit config that doesn't
+    /// run rustfmt.
+    pub fn new(file_path: &str, contents: &str) -> Option<Self> {
+        let kind = ConfigFileKind::from_path(file_path)?;
+        let contents = match kind {
+            ConfigFileKind::PreCommit => rustfmt_pre_commit_hooks(contents)?,
+            ConfigFileKind::Clippy | ConfigFileKind::EditorConfig => contents.to_string(),
+        };
+
+        Some(ConfigFile {
+            kind,
+            file_path: file_path.to_string(),
+            contents,
+        })
+    }
+}
+
+/// Extract the rustfmt related hooks from a `.pre-commit-config.yaml`.
+///
+/// Returns [None] if the config can't be parsed or doesn't run rustfmt.
+fn rustfmt_pre_commit_hooks(source: &str) -> Option<String> {
+    let mut config: serde_yaml::Value = serde_yaml::from_str(source)
+        .map_err(|err| {
+            tracing::debug!(invalid_pre_commit_config=?err);
+            err
+        })
+        .ok()?;
+
+    let repos = config.get_mut("repos")?.as_sequence_mut()?;
+    repos.retain_mut(|repo| {
+        let Some(hooks) = repo.get_mut("hooks").and_then(|h| h.as_sequence_mut()) else {
+            return false;
+        };
+        hooks.retain(is_rustfmt_hook);
+        !hooks.is_empty()
+    });
+
+    if repos.is_empty() {
+        return None;
+    }
+
+    let repos = serde_yaml::Mapping::from_iter([("repos".into(), config["repos"].clone())]);
+    serde_yaml::to_string(&repos).ok()
+}
+
+/// Hooks like `fmt`, `rustfmt` or a local hook that runs `cargo fmt`.
+fn is_rustfmt_hook(hook: &serde_yaml::Value) -> bool {
+    ["id", "name", "entry"].into_iter().any(|key| {
+        hook.get(key)
+            .and_then(|value| value.as_str())
+            .is_some_and(|value| {
+                let value = value.to_ascii_lowercase();
+                value == "fmt" || value.contains("rustfmt") || value.contains("cargo fmt")
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_local_cargo_fmt_hook() {
+        let source = r#"
+repos:
+  - repo: https://github.com/pre-commit/pre-commit-hooks
+    rev: v4.6.0
+    hooks:
+      - id: trailing-whitespace
+  - repo: local
+    hooks:
+      - id: cargo-fmt
+        name: cargo fmt
+        entry: cargo fmt --all --
+        language: system
+        types: [rust]
+      - id: cargo-check
+        name: cargo check
+        entry: cargo check
+        language: system
+"#;
+        let hooks = rustfmt_pre_commit_hooks(source).unwrap();
+        assert!(hooks.contains("cargo fmt --all --"));
+        assert!(!hooks.contains("trailing-whitespace"));
+        assert!(!hooks.contains("cargo check"));
+    }
+
+    #[test]
+    fn keeps_pre_commit_rust_fmt_hook() {
+        let source = r#"
+repos:
+  - repo: https://github.com/doublify/pre-commit-rust
+    rev: v1.0
+    hooks:
+      - id: fmt
+      - id: cargo-check
+      - id: clippy
+"#;
+        let hooks = rustfmt_pre_commit_hooks(source).unwrap();
+        assert!(hooks.contains("doublify/pre-commit-rust"));
+        assert!(hooks.contains("id: fmt"));
+        assert!(!hooks.contains("cargo-check"));
+        assert!(!hooks.contains("clippy"));
+    }
+
+    #[test]
+    fn skips_config_without_rustfmt_hook() {
+        let source = r#"
+repos:
+  - repo: https://github.com/pre-commit/pre-commit-hooks
+    rev: v4.6.0
+    hooks:
+      - id: trailing-whitespace
+      - id: end-of-file-fixer
+"#;
+        assert!(rustfmt_pre_commit_hooks(source).is_none());
+        assert!(ConfigFile::new(".pre-commit-config.yaml", source).is_none());
+    }
+
+    #[test]
+    fn skips_invalid_yaml() {
+        let source = "repos:\n  - repo: local\n    hooks: [\n";
+        assert!(rustfmt_pre_commit_hooks(source).is_none());
+        assert!(ConfigFile::new(".pre-commit-config.yaml", source).is_none());
+    }
+
+    #[test]
+    fn matches_rustfmt_hooks() {
+        let hook = |yaml: &str| serde_yaml::from_str::<serde_yaml::Value>(yaml).unwrap();
+        assert!(is_rustfmt_hook(&hook("id: fmt")));
+        assert!(is_rustfmt_hook(&hook("id: FMT")));
+        assert!(is_rustfmt_hook(&hook("id: rustfmt")));
+        assert!(is_rustfmt_hook(&hook(
+            "{id: fmt-check, entry: cargo fmt --check}"
+        )));
+        assert!(!is_rustfmt_hook(&hook("id: fmt-check")));
+        assert!(!is_rustfmt_hook(&hook("id: clippy")));
+        assert!(!is_rustfmt_hook(&hook("fmt")));
+    }
+}
@@ -1 +1,7 @@
+pub mod checkpoints;
+pub mod crawl_runs;
+pub mod denylist;
+pub mod pinned;
+pub mod repositories;
+pub mod rustfmt_configs;
 pub mod store;
@@ -0,0 +1,53 @@
+use crate::SearchCheckpoint;
+
+use anyhow::Context;
+use sqlx::types::Json;
+use sqlx::PgPool;
+
+/// Save where the search for `query` left off, replacing any earlier checkpoint for it.
+pub async fn save_checkpoint(
+    db: &PgPool,
+    query: &str,
+    checkpoint: &SearchCheckpoint,
+) -> anyhow::Result<()> {
+    let query_string = r"insert into crawl_checkpoints(query, cursor, checkpoint)
+values ($1, $2, $3)
+on conflict on constraint crawl_checkpoints_pkey
+do update set
+cursor = excluded.cursor,
+checkpoint = excluded.checkpoint,
+updated_at = now();";
+
+    sqlx::query(query_string)
+        .bind(query)
+        .bind(checkpoint.cursor())
+        .bind(Json(checkpoint))
+        .execute(db)
+        .await
+        .map(|_| ())
+        .with_context(|| format!("Failed to save the checkpoint for `{query}`"))
+}
+
+/// The last checkpoint saved for `query`, if the search hasn't finished yet.
+pub async fn load_checkpoint(db: &PgPool, query: &str) -> anyhow::Result<Option<SearchCheckpoint>> {
+    let query_string = r"select checkpoint from crawl_checkpoints where query = $1;";
+
+    sqlx::query_scalar::<_, Json<SearchCheckpoint>>(query_string)
+        .bind(query)
+        .fetch_optional(db)
+        .await
+        .map(|checkpoint| checkpoint.map(|Json(checkpoint)| checkpoint))
+        .with_context(|| format!("Failed to load the checkpoint for `{query}`"))
+}
+
+/// Delete the checkpoint for `query` once the search has finished.
+pub async fn delete_checkpoint(db: &PgPool, query: &str) -> anyhow::Result<()> {
+    let query_string = r"delete from crawl_checkpoints where query = $1;";
+
+    sqlx::query(query_string)
+        .bind(query)
+        .execute(db)
+        .await
+        .map(|_| ())
+        .with_context(|| format!("Failed to delete the checkpoint for `{query}`"))
+}
@@ -0,0 +1,93 @@
+use anyhow::Context;
+use sqlx::PgPool;
+use std::ops::AddAssign;
+
+/// How a crawl was started.
+#[derive(Debug)]
+pub struct CrawlParameters {
+    /// The subcommand that started the crawl, like `add-repo` or `refresh`
+    pub command: String,
+    /// The command line arguments, so the crawl can be run again
+    pub arguments: Vec<String>,
+    pub min_stars: Option<u32>,
+    pub max_pages: Option<u32>,
+}
+
+/// How many repositories a crawl found and what happened to them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CrawlStats {
+    /// Repositories returned by GitHub
+    pub fetched: usize,
+    /// Repositories that weren't stored before
+    pub inserted: usize,
+    /// Repositories that were already stored
+    pub updated: usize,
+    /// Repositories that were skipped because they're denylisted or don't have enough Rust
+    pub rejected: usize,
+}
+
+impl AddAssign for CrawlStats {
+    fn add_assign(&mut self, other: Self) {
+        self.fetched += other.fetched;
+        self.inserted += other.inserted;
+        self.updated += other.updated;
+        self.rejected += other.rejected;
+    }
+}
+
+/// Record the start of a crawl. Returns the `run_id` that repositories stored by the crawl
+/// are linked to.
+pub async fn start_crawl_run(db: &PgPool, parameters: &CrawlParameters) -> anyhow::Result<i64> {
+    let query = r"insert into crawl_runs(command, arguments, min_stars, max_pages)
+values ($1, $2, $3, $4)
+returning run_id;";
+
+    sqlx::query_scalar::<_, i64>(query)
+        .bind(&parameters.command)
+        .bind(&parameters.arguments)
+        .bind(parameters.min_stars.map(|stars| stars as i32))
+        .bind(parameters.max_pages.map(|pages| pages as i32))
+        .fetch_one(db)
+        .await
+        .with_context(|| "Failed to record the start of the crawl")
+}
+
+/// Record the GitHub search query a crawl used.
+pub async fn set_crawl_run_query(db: &PgPool, run_id: i64, query: &str) -> anyhow::Result<()> {
+    sqlx::query("update crawl_runs set query = $2 where run_id = $1;")
+        .bind(run_id)
+        .bind(query)
+        .execute(db)
+        .await
+        .map(|_| ())
+        .with_context(|| format!("Failed to record the query of crawl {run_id}"))
+}
+
+/// Record the end of a crawl, along with what it found and the errors it ran into.
+pub async fn finish_crawl_run(
+    db: &PgPool,
+    run_id: i64,
+    stats: &CrawlStats,
+    errors: &[String],
+) -> anyhow::Result<()> {
+    let query = r"update crawl_runs set
+finished_at = now(),
+repositories_fetched = $2,
+repositories_inserted = $3,
+repositories_updated = $4,
+repositories_rejected = $5,
+errors = $6
+where run_id = $1;";
+
+    sqlx::query(query)
+        .bind(run_id)
+        .bind(stats.fetched as i32)
+        .bind(stats.inserted as i32)
+        .bind(stats.updated as i32)
+        .bind(stats.rejected as i32)
+        .bind(errors)
+        .execute(db)
+        .await
+        .map(|_| ())
+        .with_context(|| format!("Failed to record the end of crawl {run_id}"))
+}
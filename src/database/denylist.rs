@@ -0,0 +1,401 @@
+use crate::Repository;
+
+use anyhow::Context;
+use sqlx::{PgExecutor, PgPool, Postgres, QueryBuilder};
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::str::FromStr;
+use time::OffsetDateTime;
+
+/// Identifies repositories that must never be stored in the database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DenylistEntry {
+    /// A single repository, identified by its GraphQL ID so that renames don't matter.
+    Id(String),
+    /// An `owner/name` pattern, where `*` matches any characters, e.g. `owner/*`.
+    /// Patterns are case insensitive.
+    Pattern(String),
+}
+
+impl DenylistEntry {
+    fn as_str(&self) -> &str {
+        match self {
+            Self::Id(id) => id,
+            Self::Pattern(pattern) => pattern,
+        }
+    }
+
+    fn is_pattern(&self) -> bool {
+        matches!(self, Self::Pattern(_))
+    }
+
+    /// Convert the pattern into a case insensitive SQL `like` pattern.
+    fn like_pattern(&self) -> Option<String> {
+        let Self::Pattern(pattern) = self else {
+            return None;
+        };
+        let escaped = pattern
+            .to_lowercase()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        Some(escaped.replace('*', "%"))
+    }
+
+    /// Does this entry match the repository?
+    pub fn matches(&self, github_graphql_id: &str, repo_name: &str) -> bool {
+        match self {
+            Self::Id(id) => id == github_graphql_id,
+            Self::Pattern(pattern) => {
+                glob_match(&pattern.to_lowercase(), &repo_name.to_lowercase())
+            }
+        }
+    }
+}
+
+impl FromStr for DenylistEntry {
+    type Err = String;
+
+    /// Entries containing a `/` are `owner/name` patterns, anything else must be a repository's
+    /// GraphQL ID, like `R_kgDOBQ6cWg` or the legacy `MDEwOlJlcG9zaXRvcnkxMjM0NQ==`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err("denylist entries can't be empty".to_string());
+        }
+
+        if s.contains('/') {
+            let is_part = |part: &str| {
+                !part.is_empty()
+                    && part
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '*'))
+            };
+            match s.split_once('/') {
+                Some((owner, name)) if is_part(owner) && is_part(name) => {
+                    Ok(Self::Pattern(s.to_string()))
+                }
+                _ => Err(format!("`{s}` isn't an `owner/name` pattern")),
+            }
+        } else if (s.starts_with("R_") || s.starts_with("MDEw"))
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '=' | '+'))
+        {
+            Ok(Self::Id(s.to_string()))
+        } else {
+            Err(format!(
+                "`{s}` is neither an `owner/name` pattern nor a repository's GraphQL ID"
+            ))
+        }
+    }
+}
+
+impl Display for DenylistEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Match `text` against a `pattern` where `*` matches any number of characters.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts = parts.collect::<Vec<_>>();
+    let Some(last) = parts.pop() else {
+        // There was no `*` in the pattern
+        return rest.is_empty();
+    };
+
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// A denylist entry along with why and when it was added.
+#[derive(Debug)]
+pub struct DenylistRecord {
+    pub entry: DenylistEntry,
+    pub reason: Option<String>,
+    pub denylisted_at: OffsetDateTime,
+}
+
+/// Every denylist entry, loaded into memory so that repositories can be checked before they're
+/// stored.
+#[derive(Debug, Default)]
+pub struct Denylist {
+    ids: HashSet<String>,
+    patterns: Vec<DenylistEntry>,
+}
+
+impl Denylist {
+    pub async fn load(db: impl PgExecutor<'_>) -> anyhow::Result<Self> {
+        let mut denylist = Self::default();
+        for record in denylisted_repositories(db).await? {
+            match record.entry {
+                DenylistEntry::Id(id) => {
+                    denylist.ids.insert(id);
+                }
+                pattern @ DenylistEntry::Pattern(_) => denylist.patterns.push(pattern),
+            }
+        }
+        Ok(denylist)
+    }
+
+    pub fn is_denied(&self, repo: &Repository) -> bool {
+        self.ids.contains(repo.id())
+            || self
+                .patterns
+                .iter()
+                .any(|pattern| pattern.matches(repo.id(), repo.name_with_owner()))
+    }
+
+    /// Remove denylisted repositories, logging each one that's skipped.
+    pub fn filter(&self, repositories: Vec<Repository>) -> Vec<Repository> {
+        repositories
+            .into_iter()
+            .filter(|repo| {
+                if self.is_denied(repo) {
+                    tracing::info!(repo = repo.name_with_owner(), "skipping denylisted repo");
+                    return false;
+                }
+                true
+            })
+            .collect()
+    }
+}
+
+/// Add entries to the denylist and purge any data we already have for the repositories
+/// they match.
+///
+/// Returns the names of the repositories that were purged.
+pub async fn denylist_repositories(
+    db: &PgPool,
+    entries: &[DenylistEntry],
+    reason: Option<&str>,
+) -> anyhow::Result<Vec<String>> {
+    if entries.is_empty() {
+        return Ok(vec![]);
+    }
+
+    // Postgres can't update the same row twice in one `on conflict do update`
+    let mut seen = HashSet::new();
+    let entries = entries
+        .iter()
+        .filter(|entry| seen.insert(entry.as_str()))
+        .collect::<Vec<_>>();
+
+    let mut transaction = db.begin().await?;
+    let insert_query = r"insert into denylisted_repositories(
+    entry,
+    is_pattern,
+    reason
+)
+";
+
+    let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(insert_query);
+    query_builder.push_values(&entries, |mut b, entry| {
+        b.push_bind(entry.as_str())
+            .push_bind(entry.is_pattern())
+            .push_bind(reason);
+    });
+    // Entries that were removed before are denylisted again from now on
+    query_builder.push(
+        r"
+        on conflict on constraint denylisted_repositories_pkey
+        do update set
+        reason = coalesce(excluded.reason, denylisted_repositories.reason),
+        denylisted_at = case
+            when denylisted_repositories.removed_at is null then denylisted_repositories.denylisted_at
+            else now()
+        end,
+        removed_at = null;",
+    );
+    query_builder
+        .build()
+        .execute(&mut *transaction)
+        .await
+        .with_context(|| "Failed to denylist repositories")?;
+
+    let ids = entries
+        .iter()
+        .filter(|entry| !entry.is_pattern())
+        .map(|entry| entry.as_str())
+        .collect::<Vec<_>>();
+    let like_patterns = entries
+        .iter()
+        .filter_map(|entry| entry.like_pattern())
+        .collect::<Vec<_>>();
+
+    sqlx::query(
+        "delete from pinned_repositories
+where github_graphql_id = any($1) or lower(repo_name) like any($2);",
+    )
+    .bind(&ids)
+    .bind(&like_patterns)
+    .execute(&mut *transaction)
+    .await
+    .with_context(|| "Failed to unpin denylisted repositories")?;
+
+    // Config files are removed along with the repository by `on delete cascade`
+    let purged = sqlx::query_scalar::<_, String>(
+        "delete from github_repositories
+where github_graphql_id = any($1) or lower(repo_name) like any($2)
+returning repo_name;",
+    )
+    .bind(&ids)
+    .bind(&like_patterns)
+    .fetch_all(&mut *transaction)
+    .await
+    .with_context(|| "Failed to purge denylisted repositories")?;
+
+    transaction
+        .commit()
+        .await
+        .with_context(|| "Failed to denylist repositories")?;
+    Ok(purged)
+}
+
+/// Remove entries from the denylist. Removed entries are kept with a `removed_at` date so that
+/// there's a record of them, but they no longer apply.
+///
+/// Returns the entries that were removed.
+pub async fn remove_from_denylist(
+    db: &PgPool,
+    entries: &[DenylistEntry],
+) -> anyhow::Result<Vec<String>> {
+    let entries = entries
+        .iter()
+        .map(DenylistEntry::as_str)
+        .collect::<Vec<_>>();
+
+    sqlx::query_scalar::<_, String>(
+        "update denylisted_repositories
+set removed_at = now()
+where entry = any($1) and removed_at is null
+returning entry;",
+    )
+    .bind(&entries)
+    .fetch_all(db)
+    .await
+    .with_context(|| "Failed to remove denylist entries")
+}
+
+/// Every denylist entry that hasn't been removed, in the order they were added.
+pub async fn denylisted_repositories(
+    db: impl PgExecutor<'_>,
+) -> anyhow::Result<Vec<DenylistRecord>> {
+    let query = r"select entry, is_pattern, reason, denylisted_at
+from denylisted_repositories
+where removed_at is null
+order by denylisted_at, entry;";
+
+    sqlx::query_as::<_, (String, bool, Option<String>, OffsetDateTime)>(query)
+        .fetch_all(db)
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(
+                    |(entry, is_pattern, reason, denylisted_at)| DenylistRecord {
+                        entry: if is_pattern {
+                            DenylistEntry::Pattern(entry)
+                        } else {
+                            DenylistEntry::Id(entry)
+                        },
+                        reason,
+                        denylisted_at,
+                    },
+                )
+                .collect()
+        })
+        .with_context(|| "Failed to load the denylist")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glob_match, DenylistEntry};
+
+    #[test]
+    fn glob_without_a_wildcard_matches_exactly() {
+        assert!(glob_match("rust-lang/rustfmt", "rust-lang/rustfmt"));
+        assert!(!glob_match("rust-lang/rustfmt", "rust-lang/rustfmt-fork"));
+        assert!(!glob_match("rust-lang/rustfmt", "rust-lang/rust"));
+    }
+
+    #[test]
+    fn glob_wildcards_match_any_characters() {
+        assert!(glob_match("rust-lang/*", "rust-lang/rustfmt"));
+        assert!(glob_match("rust-lang/*", "rust-lang/"));
+        assert!(!glob_match("rust-lang/*", "tokio-rs/tokio"));
+        assert!(glob_match("*/rustfmt", "someone/rustfmt"));
+        assert!(glob_match("*/rust*-bot", "someone/rustfmt-bot"));
+        assert!(!glob_match("*/rust*-bot", "someone/rustfmt-bots"));
+        assert!(glob_match("a*b*c", "abc"));
+        assert!(!glob_match("a*bc*c", "abc"));
+    }
+
+    #[test]
+    fn patterns_match_case_insensitively() {
+        let entry = "Rust-Lang/*".parse::<DenylistEntry>().unwrap();
+        assert!(entry.matches("R_kgDOBQ6cWg", "rust-lang/RUSTFMT"));
+        assert!(!entry.matches("R_kgDOBQ6cWg", "tokio-rs/tokio"));
+    }
+
+    #[test]
+    fn like_patterns_escape_sql_wildcards() {
+        let like_pattern = |pattern: &str| {
+            DenylistEntry::Pattern(pattern.to_string())
+                .like_pattern()
+                .unwrap()
+        };
+        assert_eq!(like_pattern("Owner/*"), "owner/%");
+        assert_eq!(like_pattern("owner/my_repo"), "owner/my\\_repo");
+        assert_eq!(like_pattern("owner/100%"), "owner/100\\%");
+        assert_eq!(like_pattern("owner/a\\b"), "owner/a\\\\b");
+        assert_eq!(
+            DenylistEntry::Id("R_kgDOBQ6cWg".to_string()).like_pattern(),
+            None
+        );
+    }
+
+    #[test]
+    fn parses_patterns_and_ids() {
+        assert_eq!(
+            "owner/*".parse(),
+            Ok(DenylistEntry::Pattern("owner/*".to_string()))
+        );
+        assert_eq!(
+            " R_kgDOBQ6cWg ".parse(),
+            Ok(DenylistEntry::Id("R_kgDOBQ6cWg".to_string()))
+        );
+        assert_eq!(
+            "MDEwOlJlcG9zaXRvcnkxMjM0NQ==".parse(),
+            Ok(DenylistEntry::Id(
+                "MDEwOlJlcG9zaXRvcnkxMjM0NQ==".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn rejects_entries_that_are_neither_patterns_nor_ids() {
+        for entry in [
+            "",
+            "rustfmt",
+            "owner/",
+            "/name",
+            "owner/name/extra",
+            "https://github.com/owner/name",
+            "owner/na me",
+            "R_has spaces",
+        ] {
+            assert!(entry.parse::<DenylistEntry>().is_err(), "{entry:?}");
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::Repository;
+
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use std::collections::HashSet;
+use time::OffsetDateTime;
+
+/// A repository that's always part of the corpus, no matter which search filters are used.
+#[derive(Debug)]
+pub struct PinnedRepository {
+    pub github_graphql_id: String,
+    pub repo_name: String,
+    /// Why the repository was pinned
+    pub reason: Option<String>,
+    pub pinned_at: OffsetDateTime,
+}
+
+/// Pin the repositories so that they're refreshed on every crawl.
+///
+/// Repositories that are already pinned keep their original `pinned_at` date. Repositories that
+/// are passed more than once are only pinned once.
+pub async fn pin_repositories(
+    db: &PgPool,
+    repositories: &[Repository],
+    reason: Option<&str>,
+) -> anyhow::Result<()> {
+    if repositories.is_empty() {
+        return Ok(());
+    }
+
+    let insert_query = r"insert into pinned_repositories(
+    github_graphql_id,
+    repo_name,
+    reason
+)
+";
+
+    // Postgres can't update the same row twice in one `on conflict do update`
+    let mut seen = HashSet::new();
+    let repositories = repositories.iter().filter(|repo| seen.insert(repo.id()));
+
+    let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(insert_query);
+    query_builder.push_values(repositories, |mut b, repo| {
+        b.push_bind(repo.id().to_string())
+            .push_bind(repo.name_with_owner().to_string())
+            .push_bind(reason);
+    });
+    query_builder.push(
+        r"
+        on conflict on constraint pinned_repositories_pkey
+        do update set
+        repo_name = excluded.repo_name,
+        reason = coalesce(excluded.reason, pinned_repositories.reason);",
+    );
+
+    query_builder
+        .build()
+        .execute(db)
+        .await
+        .map(|_| ())
+        .with_context(|| "Failed to pin repositories")
+}
+
+/// Unpin repositories by their name with owner, like `rust-lang/rustfmt`, or their GraphQL ID.
+///
+/// Returns the repositories that were unpinned.
+pub async fn unpin_repositories(db: &PgPool, repos: &[String]) -> anyhow::Result<Vec<String>> {
+    let query = r"delete from pinned_repositories
+where lower(repo_name) = any(select lower(unnest($1::text[]))) or github_graphql_id = any($1)
+returning repo_name;";
+
+    sqlx::query_scalar::<_, String>(query)
+        .bind(repos)
+        .fetch_all(db)
+        .await
+        .with_context(|| "Failed to unpin repositories")
+}
+
+/// Every pinned repository, ordered by name.
+pub async fn pinned_repositories(db: &PgPool) -> anyhow::Result<Vec<PinnedRepository>> {
+    let query = r"select github_graphql_id, repo_name, reason, pinned_at
+from pinned_repositories
+order by lower(repo_name);";
+
+    sqlx::query_as::<_, (String, String, Option<String>, OffsetDateTime)>(query)
+        .fetch_all(db)
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(
+                    |(github_graphql_id, repo_name, reason, pinned_at)| PinnedRepository {
+                        github_graphql_id,
+                        repo_name,
+                        reason,
+                        pinned_at,
+                    },
+                )
+                .collect()
+        })
+        .with_context(|| "Failed to load pinned repositories")
+}
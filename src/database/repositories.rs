@@ -0,0 +1,107 @@
+use crate::Repository;
+
+use anyhow::Context;
+use sqlx::{PgExecutor, PgPool};
+use std::collections::HashSet;
+
+/// The GraphQL IDs of the stored repositories, starting with the ones that were checked the
+/// longest time ago. Repositories that are gone are skipped unless `include_gone` is set.
+pub async fn stored_repository_ids(
+    db: &PgPool,
+    limit: Option<i64>,
+    include_gone: bool,
+) -> anyhow::Result<Vec<String>> {
+    let query = r"select github_graphql_id
+from github_repositories
+where $2 or gone_at is null
+order by record_last_checked, github_graphql_id
+limit $1;";
+
+    sqlx::query_scalar::<_, String>(query)
+        .bind(limit)
+        .bind(include_gone)
+        .fetch_all(db)
+        .await
+        .context("Failed to load the stored repositories")
+}
+
+/// Mark repositories that were deleted or made private as gone.
+///
+/// Returns the names of the repositories that weren't already marked as gone.
+pub async fn mark_repositories_gone(
+    db: &PgPool,
+    github_graphql_ids: &[String],
+) -> anyhow::Result<Vec<String>> {
+    let query = r"update github_repositories
+set gone_at = now()
+where github_graphql_id = any($1) and gone_at is null
+returning repo_name;";
+
+    sqlx::query_scalar::<_, String>(query)
+        .bind(github_graphql_ids)
+        .fetch_all(db)
+        .await
+        .context("Failed to mark repositories as gone")
+}
+
+/// The GraphQL IDs of the repositories that aren't stored yet, or whose `pushed_at` date or
+/// latest commit differ from the stored ones. Only these need to be formatted again.
+pub async fn changed_repositories(
+    db: impl PgExecutor<'_>,
+    repositories: &[Repository],
+) -> anyhow::Result<HashSet<String>> {
+    let mut ids = Vec::with_capacity(repositories.len());
+    let mut pushed_at = Vec::with_capacity(repositories.len());
+    let mut latest_commits = Vec::with_capacity(repositories.len());
+    for repo in repositories {
+        ids.push(repo.id().to_string());
+        pushed_at.push(repo.pushed_at());
+        latest_commits.push(repo.commit_hash().map(str::to_string));
+    }
+
+    let query = r"select fetched.github_graphql_id
+from unnest($1::text[], $2::timestamptz[], $3::text[])
+    as fetched(github_graphql_id, pushed_at, latest_commit)
+left join github_repositories as stored using (github_graphql_id)
+where stored.github_graphql_id is null
+or stored.pushed_at is distinct from fetched.pushed_at
+or stored.latest_commit is distinct from fetched.latest_commit;";
+
+    sqlx::query_scalar::<_, String>(query)
+        .bind(&ids)
+        .bind(&pushed_at)
+        .bind(&latest_commits)
+        .fetch_all(db)
+        .await
+        .map(|ids| ids.into_iter().collect())
+        .context("Failed to find the repositories that changed")
+}
+
+/// The GraphQL IDs of the repositories whose rustfmt configs weren't fetched at their latest
+/// commit yet. That includes repositories that aren't stored yet, repositories that were stored
+/// without fetching their configs, and repositories whose configs couldn't be fetched.
+pub async fn repositories_missing_configs(
+    db: impl PgExecutor<'_>,
+    repositories: &[Repository],
+) -> anyhow::Result<HashSet<String>> {
+    let mut ids = Vec::with_capacity(repositories.len());
+    let mut latest_commits = Vec::with_capacity(repositories.len());
+    for repo in repositories {
+        ids.push(repo.id().to_string());
+        latest_commits.push(repo.commit_hash().map(str::to_string));
+    }
+
+    let query = r"select fetched.github_graphql_id
+from unnest($1::text[], $2::text[]) as fetched(github_graphql_id, latest_commit)
+left join github_repositories as stored using (github_graphql_id)
+where stored.github_graphql_id is null
+or stored.configs_fetched_commit is distinct from fetched.latest_commit;";
+
+    sqlx::query_scalar::<_, String>(query)
+        .bind(&ids)
+        .bind(&latest_commits)
+        .fetch_all(db)
+        .await
+        .map(|ids| ids.into_iter().collect())
+        .context("Failed to find the repositories that are missing configs")
+}
@@ -0,0 +1,70 @@
+use anyhow::Context;
+use sqlx::types::Json;
+use sqlx::PgPool;
+
+/// Find the rustfmt config stored for a repository.
+///
+/// When a repository contains multiple configs the one closest to the repository root is used.
+pub async fn find_config_by_repo_name(
+    db: &PgPool,
+    repo_name: &str,
+) -> anyhow::Result<Option<serde_json::Value>> {
+    let query = r"select config
+from rustfmt_configuration_files
+join github_repositories using (github_graphql_id)
+where lower(repo_name) = lower($1) and config is not null
+order by length(file_path), file_path
+limit 1;";
+
+    sqlx::query_scalar::<_, Json<serde_json::Value>>(query)
+        .bind(repo_name)
+        .fetch_optional(db)
+        .await
+        .map(|config| config.map(|Json(config)| config))
+        .with_context(|| format!("Failed to find the rustfmt config for {repo_name}"))
+}
+
+/// Find a stored rustfmt config by its hash.
+///
+/// A config's hash is the md5 hash of its `jsonb` text representation, which doesn't depend on
+/// how the original `rustfmt.toml` was formatted. Any unique prefix of the hash can be used.
+pub async fn find_config_by_hash(
+    db: &PgPool,
+    hash: &str,
+) -> anyhow::Result<Option<serde_json::Value>> {
+    let query = r"select distinct config
+from rustfmt_configuration_files
+where md5(config::text) like $1 || '%'
+limit 2;";
+
+    let mut configs = sqlx::query_scalar::<_, Json<serde_json::Value>>(query)
+        .bind(hash)
+        .fetch_all(db)
+        .await
+        .with_context(|| format!("Failed to find the rustfmt config with hash {hash}"))?;
+
+    if configs.len() > 1 {
+        anyhow::bail!("config hash {hash} is ambiguous");
+    }
+
+    Ok(configs.pop().map(|Json(config)| config))
+}
+
+/// Every stored rustfmt config along with the repository and path it was found at.
+pub async fn all_configs(db: &PgPool) -> anyhow::Result<Vec<(String, String, serde_json::Value)>> {
+    let query = r"select repo_name, file_path, config
+from rustfmt_configuration_files
+join github_repositories using (github_graphql_id)
+where config is not null
+order by repo_name, file_path;";
+
+    sqlx::query_as::<_, (String, String, Json<serde_json::Value>)>(query)
+        .fetch_all(db)
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(|(repo_name, file_path, Json(config))| (repo_name, file_path, config))
+                .collect()
+        })
+        .context("Failed to load rustfmt configs")
+}
@@ -1,13 +1,67 @@
-use crate::Repository;
+use super::crawl_runs::CrawlStats;
+use super::denylist::Denylist;
+use super::repositories::changed_repositories;
+use crate::github::RUSTFMT_CONFIG_FILE_NAMES;
+use crate::{Repository, RustfmtConfigFile};
 
 use anyhow::Context;
+use sqlx::types::Json;
 use sqlx::QueryBuilder;
-use sqlx::{PgPool, Postgres};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashSet;
 
-pub async fn store_in_db<R>(db: PgPool, repositories: R) -> anyhow::Result<()>
+/// Store the repositories and their config files. Denylisted repositories are skipped.
+///
+/// Repositories that are new or changed since they were last stored are queued in
+/// `dirty_repositories`. Stored repositories are linked to the crawl `run_id`, if there is one.
+/// Returns how many repositories were inserted, updated and skipped.
+pub async fn store_in_db<R>(
+    db: PgPool,
+    repositories: R,
+    run_id: Option<i64>,
+) -> anyhow::Result<CrawlStats>
 where
     R: Iterator<Item = Repository>,
 {
+    let mut transaction = db.begin().await?;
+    let denylist = Denylist::load(&mut *transaction).await?;
+    let repositories = repositories.collect::<Vec<_>>();
+    let fetched = repositories.len();
+    let repositories = denylist.filter(repositories);
+    let mut stats = CrawlStats {
+        fetched,
+        rejected: fetched - repositories.len(),
+        ..CrawlStats::default()
+    };
+    if repositories.is_empty() {
+        return Ok(stats);
+    }
+
+    let changed = changed_repositories(&mut *transaction, &repositories).await?;
+    record_name_changes(&mut transaction, &repositories).await?;
+    let inserted = store_repositories(&mut transaction, &repositories, run_id).await?;
+    store_config_files(&mut transaction, &repositories).await?;
+    let changed = repositories
+        .iter()
+        .filter(|repo| changed.contains(repo.id()))
+        .collect::<Vec<_>>();
+    mark_dirty(&mut transaction, &changed).await?;
+    transaction
+        .commit()
+        .await
+        .with_context(|| "Failed to store in the database")?;
+
+    stats.inserted = inserted.iter().filter(|inserted| **inserted).count();
+    stats.updated = inserted.len() - stats.inserted;
+    Ok(stats)
+}
+
+/// Insert or update the repositories. Returns whether each repository was newly inserted.
+async fn store_repositories(
+    transaction: &mut Transaction<'_, Postgres>,
+    repositories: &[Repository],
+    run_id: Option<i64>,
+) -> anyhow::Result<Vec<bool>> {
     // sqlx still has limited support for inserting multiple items.
     // The `QueryBuilder` API seems to be the best way to do it at this point.
     // https://github.com/launchbadge/sqlx/issues/294#issuecomment-1912678387
@@ -21,7 +75,8 @@ where
     percent_of_code_in_rust,
     archived_at,
     pushed_at,
-    updated_at
+    updated_at,
+    run_id
 )
 ";
 
@@ -33,11 +88,12 @@ where
             .push_bind(repo.git_url().to_string())
             .push_bind(repo.is_fork())
             .push_bind(repo.is_locked())
-            .push_bind(repo.commit_hash().to_string())
+            .push_bind(repo.commit_hash())
             .push_bind(repo.percent_of_code_in_rust())
             .push_bind(repo.archived_at())
             .push_bind(repo.pushed_at())
-            .push_bind(repo.updated_at());
+            .push_bind(repo.updated_at())
+            .push_bind(run_id);
     });
     query_builder.push(
         r"
@@ -52,13 +108,222 @@ where
         archived_at = excluded.archived_at,
         pushed_at = excluded.pushed_at,
         updated_at = excluded.updated_at,
-        record_last_updated = now();",
+        record_last_updated = case
+            when github_repositories.pushed_at is distinct from excluded.pushed_at
+            or github_repositories.latest_commit is distinct from excluded.latest_commit
+            then now()
+            else github_repositories.record_last_updated
+        end,
+        record_last_checked = now(),
+        gone_at = null,
+        run_id = coalesce(excluded.run_id, github_repositories.run_id)
+        returning (xmax = 0) as inserted;",
+    );
+
+    // Rows that were just inserted haven't been locked by an update, so their `xmax` is 0
+    query_builder
+        .build_query_scalar::<bool>()
+        .fetch_all(&mut **transaction)
+        .await
+        .with_context(|| "Failed to store in the database")
+}
+
+/// Queue the repositories so they're cloned and formatted again. Repositories that are already
+/// queued are requeued at their latest commit.
+async fn mark_dirty(
+    transaction: &mut Transaction<'_, Postgres>,
+    repositories: &[&Repository],
+) -> anyhow::Result<()> {
+    if repositories.is_empty() {
+        return Ok(());
+    }
+
+    let insert_query = r"insert into dirty_repositories(github_graphql_id, latest_commit)
+";
+
+    let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(insert_query);
+    query_builder.push_values(repositories, |mut b, repo| {
+        b.push_bind(repo.id().to_string())
+            .push_bind(repo.commit_hash());
+    });
+    query_builder.push(
+        r"
+        on conflict on constraint dirty_repositories_pkey
+        do update set
+        latest_commit = excluded.latest_commit,
+        marked_at = now();",
     );
 
     query_builder
         .build()
-        .execute(&db)
+        .execute(&mut **transaction)
         .await
         .map(|_| ())
-        .with_context(|| "Failed to store in the database")
+        .with_context(|| "Failed to mark repositories as changed")
+}
+
+/// Remember the old name of every repository that was renamed or transferred since it was
+/// last stored.
+async fn record_name_changes(
+    transaction: &mut Transaction<'_, Postgres>,
+    repositories: &[Repository],
+) -> anyhow::Result<()> {
+    let (ids, names): (Vec<_>, Vec<_>) = repositories
+        .iter()
+        .map(|repo| (repo.id().to_string(), repo.name_with_owner().to_string()))
+        .unzip();
+
+    let query = r"insert into repository_name_history(github_graphql_id, old_repo_name, new_repo_name)
+select github_graphql_id, stored.repo_name, fetched.repo_name
+from github_repositories as stored
+join unnest($1::text[], $2::text[]) as fetched(github_graphql_id, repo_name)
+using (github_graphql_id)
+where stored.repo_name <> fetched.repo_name;";
+
+    sqlx::query(query)
+        .bind(&ids)
+        .bind(&names)
+        .execute(&mut **transaction)
+        .await
+        .map(|_| ())
+        .with_context(|| "Failed to record repository name changes")
+}
+
+/// Replace the formatting related config files stored for each repository
+/// with the ones found at the repository's latest commit.
+async fn store_config_files(
+    transaction: &mut Transaction<'_, Postgres>,
+    repositories: &[Repository],
+) -> anyhow::Result<()> {
+    let ids = repositories
+        .iter()
+        .map(|repo| repo.id().to_string())
+        .collect::<Vec<_>>();
+
+    sqlx::query("delete from repository_config_files where github_graphql_id = any($1);")
+        .bind(&ids)
+        .execute(&mut **transaction)
+        .await
+        .with_context(|| "Failed to remove outdated config files")?;
+
+    let config_files = repositories
+        .iter()
+        .flat_map(|repo| {
+            repo.config_files()
+                .into_iter()
+                .map(move |config_file| (repo, config_file))
+        })
+        .collect::<Vec<_>>();
+
+    if config_files.is_empty() {
+        return Ok(());
+    }
+
+    let insert_query = r"insert into repository_config_files(
+    github_graphql_id,
+    kind,
+    file_path,
+    latest_commit,
+    contents
+)
+";
+
+    let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(insert_query);
+    query_builder.push_values(config_files, |mut b, (repo, config_file)| {
+        b.push_bind(repo.id().to_string())
+            .push_bind(config_file.kind.as_str())
+            .push_bind(config_file.file_path)
+            .push_bind(repo.commit_hash())
+            .push_bind(config_file.contents);
+    });
+
+    query_builder
+        .build()
+        .execute(&mut **transaction)
+        .await
+        .map(|_| ())
+        .with_context(|| "Failed to store config files in the database")
+}
+
+/// Replace the rustfmt configs stored at the root of each repository with the fetched configs.
+///
+/// `github_graphql_ids` are the repositories that were looked up, so stored configs are only
+/// replaced for them, and they're recorded as having their configs fetched at their latest
+/// commit. Configs that aren't valid TOML are stored without a parsed `config`. Configs for
+/// repositories that weren't stored, like denylisted repositories, are skipped.
+pub async fn store_rustfmt_configs(
+    db: PgPool,
+    github_graphql_ids: &[String],
+    mut configs: Vec<RustfmtConfigFile>,
+) -> anyhow::Result<()> {
+    let mut transaction = db.begin().await?;
+    let stored_ids = sqlx::query_scalar::<_, String>(
+        "select github_graphql_id from github_repositories where github_graphql_id = any($1);",
+    )
+    .bind(github_graphql_ids)
+    .fetch_all(&mut *transaction)
+    .await
+    .with_context(|| "Failed to find the stored repositories")?
+    .into_iter()
+    .collect::<HashSet<_>>();
+    configs.retain(|config_file| stored_ids.contains(&config_file.github_graphql_id));
+
+    sqlx::query(
+        "delete from rustfmt_configuration_files
+where github_graphql_id = any($1) and file_path = any($2);",
+    )
+    .bind(github_graphql_ids)
+    .bind(RUSTFMT_CONFIG_FILE_NAMES)
+    .execute(&mut *transaction)
+    .await
+    .with_context(|| "Failed to remove outdated rustfmt configs")?;
+
+    if !configs.is_empty() {
+        let insert_query = r"insert into rustfmt_configuration_files(
+    github_graphql_id,
+    latest_commit,
+    file_path,
+    config
+)
+";
+
+        let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(insert_query);
+        query_builder.push_values(configs, |mut b, config_file| {
+            let config = toml::from_str::<serde_json::Value>(&config_file.contents)
+                .map_err(|err| {
+                    tracing::warn!(
+                        github_graphql_id = config_file.github_graphql_id,
+                        invalid_rustfmt_config=?err
+                    );
+                    err
+                })
+                .ok();
+
+            b.push_bind(config_file.github_graphql_id)
+                .push_bind(config_file.latest_commit)
+                .push_bind(config_file.file_path)
+                .push_bind(config.map(Json));
+        });
+
+        query_builder
+            .build()
+            .execute(&mut *transaction)
+            .await
+            .with_context(|| "Failed to store rustfmt configs in the database")?;
+    }
+
+    sqlx::query(
+        "update github_repositories
+set configs_fetched_commit = latest_commit
+where github_graphql_id = any($1);",
+    )
+    .bind(github_graphql_ids)
+    .execute(&mut *transaction)
+    .await
+    .with_context(|| "Failed to record which commit the rustfmt configs were fetched at")?;
+
+    transaction
+        .commit()
+        .await
+        .with_context(|| "Failed to store rustfmt configs in the database")
 }
@@ -1,10 +1,123 @@
+mod blocking;
+mod client;
+mod code_search;
+mod configs;
+mod error;
 mod graphql;
+mod lookup;
+mod nodes;
+mod owner;
+mod partition;
+mod qualifiers;
 mod search;
-use graphql::RepositoryInfo as GraphQLRepoInfo;
+use crate::config_files::ConfigFile;
+use client::GitHubClient;
+use graphql::{GraphQLData, GraphQLResponse, RepositoryInfo as GraphQLRepoInfo};
+use reqwest::header;
+use serde::de::DeserializeOwned;
 use std::fmt::{Debug, Display};
 
-pub use graphql::ProgrammingLanguage;
-pub use search::{GitHubRepoSearch, RepoSearchResults};
+pub use client::{PointBudget, RetryPolicy};
+pub use code_search::{CodeSearchResults, GitHubCodeSearch};
+pub use configs::{FetchedConfigs, RustfmtConfigFetcher, RustfmtConfigFile};
+pub use error::SearchError;
+pub(crate) use graphql::RUSTFMT_CONFIG_FILE_NAMES;
+pub use graphql::{GraphQLError, GraphQLPathSegment, ProgrammingLanguage, RejectedNode};
+pub use lookup::{GitHubRepoLookup, RepoLookupResults};
+pub use owner::{GitHubOwnerRepos, OwnerRepoResults};
+pub use search::{GitHubRepoSearch, RepoSearchResults, SearchCheckpoint};
+
+/// Build a client that authenticates every request to the GitHub API with `api_key`.
+fn github_client(api_key: &str, user_agent: &str) -> Option<GitHubClient> {
+    let mut headers = header::HeaderMap::new();
+
+    let bearer_token = header::HeaderValue::from_str(&format!("Bearer {}", api_key)).ok()?;
+    headers.insert(header::AUTHORIZATION, bearer_token);
+    reqwest::ClientBuilder::new()
+        .user_agent(user_agent)
+        .default_headers(headers)
+        .build()
+        .ok()
+        .map(GitHubClient::new)
+}
+
+/// Send a GraphQL query to GitHub and deserialize the `data` from the response.
+///
+/// Responses with partial `data` are returned along with the errors for the paths that failed.
+/// Failures are logged before they're returned.
+async fn post_graphql<T: DeserializeOwned>(
+    client: &GitHubClient,
+    operation_name: &str,
+    query: &str,
+    variables: serde_json::Value,
+) -> Result<GraphQLData<T>, SearchError> {
+    let body = serde_json::json!({
+        "operationName": operation_name,
+        "query": query,
+        "variables": variables
+    });
+
+    let request_body = body.to_string();
+    tracing::trace!(request_body=?request_body);
+
+    let text = client.post_graphql(request_body).await.map_err(|err| {
+        tracing::error!(request_error=?err);
+        err
+    })?;
+
+    tracing::trace!(response_body = text);
+    graphql_data(client, operation_name, text)
+}
+
+/// Deserialize a GraphQL response, keeping the errors that came with partial `data`.
+/// Responses without any usable `data` are returned as [SearchError::GraphQL].
+fn graphql_data<T: DeserializeOwned>(
+    client: &GitHubClient,
+    operation_name: &str,
+    text: String,
+) -> Result<GraphQLData<T>, SearchError> {
+    let GraphQLResponse { data, errors } = GraphQLResponse::<serde_json::Value>::new(text)
+        .map_err(|err| {
+            tracing::error!(serialization_error=?err);
+            SearchError::Deserialization(err)
+        })?;
+
+    let Some(data) = data.filter(|data| !data.is_null()) else {
+        tracing::error!(graphql_response_errors=?errors);
+        return Err(SearchError::GraphQL(errors));
+    };
+    let data = match deserialize_graphql_data(client, data) {
+        Ok(data) => data,
+        // A field we can't do without was `null` because of the errors
+        Err(_) if !errors.is_empty() => {
+            tracing::error!(graphql_response_errors=?errors);
+            return Err(SearchError::GraphQL(errors));
+        }
+        Err(err) => {
+            tracing::error!(serialization_error=?err);
+            return Err(SearchError::Deserialization(err));
+        }
+    };
+
+    for error in &errors {
+        tracing::warn!(operation_name, graphql_response_err = %error, "partial GraphQL response");
+    }
+    Ok(GraphQLData { data, errors })
+}
+
+/// Deserialize the `data` of a GraphQL response after recording the cost of the query.
+fn deserialize_graphql_data<T: DeserializeOwned>(
+    client: &GitHubClient,
+    mut data: serde_json::Value,
+) -> Result<T, serde_json::Error> {
+    let rate_limit = data
+        .as_object_mut()
+        .and_then(|data| data.remove("rateLimit"));
+    if let Some(rate_limit) = rate_limit {
+        client.record_rate_limit(&serde_json::from_value(rate_limit)?);
+    }
+    serde_json::from_value(data)
+}
 
 pub struct Repository {
     inner: RepositoryInner,
@@ -53,7 +166,8 @@ impl Repository {
         }
     }
 
-    pub fn commit_hash(&self) -> &str {
+    /// The latest commit on the default branch. Empty repositories don't have any commits.
+    pub fn commit_hash(&self) -> Option<&str> {
         match &self.inner {
             RepositoryInner::GitHub(repo) => repo.commit_hash(),
         }
@@ -88,6 +202,17 @@ impl Repository {
             RepositoryInner::GitHub(repo) => repo.updated_at(),
         }
     }
+
+    /// Formatting related config files like `clippy.toml` and `.editorconfig`
+    /// found at the root of the repository.
+    pub fn config_files(&self) -> Vec<ConfigFile> {
+        match &self.inner {
+            RepositoryInner::GitHub(repo) => repo
+                .config_files()
+                .filter_map(|(path, contents)| ConfigFile::new(path, contents))
+                .collect(),
+        }
+    }
 }
 
 impl Display for Repository {
@@ -0,0 +1,25 @@
+use std::future::Future;
+
+/// Drives the async GitHub API from blocking code, like the [Iterator] impls of the search
+/// results.
+///
+/// The runtime is only created the first time it's needed, so async callers that never use the
+/// blocking API don't pay for it. Blocking code must not be called from within another async
+/// runtime.
+#[derive(Default)]
+pub(super) struct BlockingRuntime {
+    runtime: Option<tokio::runtime::Runtime>,
+}
+
+impl BlockingRuntime {
+    pub(super) fn block_on<F: Future>(&mut self, future: F) -> F::Output {
+        self.runtime
+            .get_or_insert_with(|| {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .expect("can build a tokio runtime")
+            })
+            .block_on(future)
+    }
+}
@@ -0,0 +1,442 @@
+use super::error::SearchError;
+use super::graphql::{GraphQLResponse, RateLimit, GITHUB_GRAPHQL_URL};
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use reqwest::{RequestBuilder, Response};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use time::OffsetDateTime;
+
+/// How many times we'll wait out a rate limit before giving up on a request.
+pub(super) const MAX_RATE_LIMIT_WAITS: usize = 5;
+
+/// GitHub asks clients that hit a secondary rate limit without a `retry-after` header to wait
+/// at least a minute before trying again.
+/// <https://docs.github.com/en/rest/using-the-rest-api/rate-limits-for-the-rest-api#exceeding-the-rate-limit>
+const SECONDARY_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// A budget of GraphQL rate limit points that can be shared by everything making requests
+/// during a run.
+///
+/// ```no_run
+/// # use rustfmt_user_config_db::{GitHubRepoSearch, PointBudget};
+/// let budget = PointBudget::new(Some(500));
+/// let mut github_search = GitHubRepoSearch::new(&"MY_API_TOKEN");
+/// github_search.max_pages(100).point_budget(&budget);
+/// let repositories = github_search.into_iter().collect::<Vec<_>>();
+/// println!("Found {} repositories for {} points", repositories.len(), budget.points_spent());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PointBudget {
+    inner: Arc<PointBudgetInner>,
+}
+
+#[derive(Debug, Default)]
+struct PointBudgetInner {
+    /// [None] when there's no limit on how many points can be spent
+    max_points: Option<u64>,
+    points_spent: AtomicU64,
+}
+
+impl PointBudget {
+    /// A budget that allows up to `max_points` points to be spent. [None] means there's no limit.
+    pub fn new(max_points: Option<u64>) -> Self {
+        Self {
+            inner: Arc::new(PointBudgetInner {
+                max_points,
+                points_spent: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// The number of points spent so far.
+    pub fn points_spent(&self) -> u64 {
+        self.inner.points_spent.load(Ordering::Relaxed)
+    }
+
+    /// Has every point in the budget been spent?
+    pub fn is_exhausted(&self) -> bool {
+        self.inner
+            .max_points
+            .is_some_and(|max_points| self.points_spent() >= max_points)
+    }
+
+    fn spend(&self, points: u64) {
+        self.inner.points_spent.fetch_add(points, Ordering::Relaxed);
+    }
+}
+
+/// The rate limit resource GraphQL queries count against.
+const GRAPHQL_RESOURCE: &str = "graphql";
+
+/// How requests that fail with a retryable error are retried.
+///
+/// The delay before each retry doubles, starting at `base_delay` and never exceeding
+/// `max_delay`. A random jitter of up to half the delay is subtracted so that concurrent
+/// crawls don't retry in lockstep, but retries never wait less than `base_delay`.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// How many times to retry a request. Defaults to 3
+    max_retries: u32,
+    /// The delay before the first retry. Defaults to 1 second
+    base_delay: Duration,
+    /// The longest we'll wait between retries. Defaults to 60 seconds
+    max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Retry failed requests up to `max_retries` times. Pass 0 to never retry.
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            ..Self::default()
+        }
+    }
+
+    /// Set the delay before the first retry.
+    pub fn base_delay(&mut self, base_delay: Duration) -> &mut Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Set the longest delay between retries.
+    pub fn max_delay(&mut self, max_delay: Duration) -> &mut Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// The delay before retrying for the `attempt + 1`th time.
+    fn delay(&self, attempt: u32) -> Duration {
+        // Only used for jitter, so the quality of the randomness doesn't matter
+        let random = RandomState::new().build_hasher().finish();
+        self.jittered_delay(attempt, (random % 1000) as f64 / 1000.0)
+    }
+
+    /// The delay before retrying for the `attempt + 1`th time, with `jitter` between 0 and 1
+    /// picking how much of the jitter range to subtract. Never shorter than the base delay.
+    fn jittered_delay(&self, attempt: u32, jitter: f64) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let jitter = delay.mul_f64(jitter.clamp(0.0, 1.0) / 2.0);
+        (delay - jitter).max(self.base_delay.min(self.max_delay))
+    }
+}
+
+/// What we last heard from GitHub about the state of a rate limit.
+#[derive(Debug, Default)]
+struct RateLimitState {
+    remaining: Option<u64>,
+    reset_at: Option<OffsetDateTime>,
+}
+
+/// A GitHub API client that waits out rate limits instead of failing requests.
+///
+/// Rate limit information is read from the `x-ratelimit-*` and `retry-after` headers of every
+/// response, and from the `rateLimit` object selected in every GraphQL query. GitHub tracks
+/// separate limits for each resource, like `graphql` or `code_search`, so we do too.
+#[derive(Debug)]
+pub(super) struct GitHubClient {
+    client: reqwest::Client,
+    budget: PointBudget,
+    retry_policy: RetryPolicy,
+    /// The state of the rate limit of each resource
+    state: Mutex<HashMap<String, RateLimitState>>,
+}
+
+impl GitHubClient {
+    pub(super) fn new(client: reqwest::Client) -> Self {
+        Self {
+            client,
+            budget: PointBudget::default(),
+            retry_policy: RetryPolicy::default(),
+            state: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Share the `budget` with this client. Requests fail once the budget is exhausted.
+    pub(super) fn set_point_budget(&mut self, budget: PointBudget) {
+        self.budget = budget;
+    }
+
+    pub(super) fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    pub(super) fn get(&self, url: &str) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    /// Send a GraphQL request body to GitHub and return the response text.
+    pub(super) async fn post_graphql(&self, request_body: String) -> Result<String, SearchError> {
+        self.send(
+            self.client.post(GITHUB_GRAPHQL_URL).body(request_body),
+            GRAPHQL_RESOURCE,
+        )
+        .await
+    }
+
+    /// Send the request and return the response text.
+    ///
+    /// Retryable failures, like timeouts and `502 Bad Gateway` responses, are retried with
+    /// jittered exponential backoff according to the client's [RetryPolicy]. `resource` is the
+    /// rate limit the request counts against.
+    pub(super) async fn send(
+        &self,
+        request: RequestBuilder,
+        resource: &str,
+    ) -> Result<String, SearchError> {
+        let mut attempt = 0;
+        loop {
+            match self.send_once(&request, resource).await {
+                Err(err) if err.is_retryable() && attempt < self.retry_policy.max_retries => {
+                    let delay = self.retry_policy.delay(attempt);
+                    attempt += 1;
+                    tracing::warn!(
+                        error = %err,
+                        attempt,
+                        delay_ms = delay.as_millis() as u64,
+                        "retrying GitHub request"
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Send the request once, first waiting for the rate limit to reset if we've run out of
+    /// requests.
+    ///
+    /// Responses that say we've hit a primary or secondary rate limit are sent again once the
+    /// rate limit resets. That includes GraphQL responses with a `RATE_LIMITED` error, which
+    /// GitHub sends with a `200 OK`. Any other unsuccessful response is an error.
+    async fn send_once(
+        &self,
+        request: &RequestBuilder,
+        resource: &str,
+    ) -> Result<String, SearchError> {
+        for _ in 0..MAX_RATE_LIMIT_WAITS {
+            if self.budget.is_exhausted() {
+                return Err(SearchError::BudgetExhausted {
+                    points_spent: self.budget.points_spent(),
+                });
+            }
+            self.wait_for_reset(resource).await;
+
+            let response = request
+                .try_clone()
+                .expect("GitHub requests don't have streaming bodies")
+                .send()
+                .await
+                .map_err(SearchError::Transport)?;
+            self.update_from_headers(response.headers(), resource);
+
+            let status = response.status();
+            let wait = rate_limit_wait(&response);
+            let text = response.text().await.map_err(SearchError::Transport)?;
+            let wait = if status.is_success() {
+                if resource != GRAPHQL_RESOURCE || !is_graphql_rate_limited(&text) {
+                    return Ok(text);
+                }
+                self.reset_wait(resource)
+                    .unwrap_or(SECONDARY_RATE_LIMIT_WAIT)
+            } else {
+                match wait {
+                    Some(wait) => wait,
+                    // Secondary rate limits don't always come with a `retry-after` header
+                    None if status == StatusCode::FORBIDDEN
+                        && text.contains("secondary rate limit") =>
+                    {
+                        SECONDARY_RATE_LIMIT_WAIT
+                    }
+                    None if status == StatusCode::UNAUTHORIZED => return Err(SearchError::Auth),
+                    None => return Err(SearchError::Status { status, body: text }),
+                }
+            };
+            tracing::warn!(
+                status = status.as_u16(),
+                wait_seconds = wait.as_secs(),
+                "hit a GitHub rate limit"
+            );
+            tokio::time::sleep(wait).await;
+        }
+        Err(SearchError::RateLimited)
+    }
+
+    /// Charge the cost of a GraphQL query to the budget and remember how many points are left.
+    pub(super) fn record_rate_limit(&self, rate_limit: &RateLimit) {
+        tracing::debug!(
+            cost = rate_limit.cost,
+            remaining = rate_limit.remaining,
+            "graphql rate limit"
+        );
+        self.budget.spend(rate_limit.cost);
+
+        let mut state = self.state.lock().expect("rate limit lock isn't poisoned");
+        let state = state.entry(GRAPHQL_RESOURCE.to_string()).or_default();
+        state.remaining = Some(rate_limit.remaining);
+        state.reset_at = Some(rate_limit.reset_at);
+    }
+
+    fn update_from_headers(&self, headers: &HeaderMap, resource: &str) {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        let number = |name: &str| header(name).and_then(|value| value.parse::<i64>().ok());
+
+        let resource = header("x-ratelimit-resource").unwrap_or(resource);
+        let mut state = self.state.lock().expect("rate limit lock isn't poisoned");
+        let state = state.entry(resource.to_string()).or_default();
+        if let Some(remaining) = number("x-ratelimit-remaining") {
+            state.remaining = u64::try_from(remaining).ok();
+        }
+        if let Some(reset) = number("x-ratelimit-reset") {
+            state.reset_at = OffsetDateTime::from_unix_timestamp(reset).ok();
+        }
+    }
+
+    /// How long until the `resource`'s rate limit resets, if we know when that is.
+    fn reset_wait(&self, resource: &str) -> Option<Duration> {
+        let state = self.state.lock().expect("rate limit lock isn't poisoned");
+        let reset_at = state.get(resource)?.reset_at?;
+        // Give GitHub a moment to actually reset the limit
+        Some(until(reset_at).unwrap_or_default() + Duration::from_secs(1))
+    }
+
+    /// Sleep until the `resource`'s rate limit resets if there are no requests left.
+    async fn wait_for_reset(&self, resource: &str) {
+        let wait = {
+            let state = self.state.lock().expect("rate limit lock isn't poisoned");
+            match state.get(resource) {
+                Some(RateLimitState {
+                    remaining: Some(0),
+                    reset_at: Some(reset_at),
+                }) => until(*reset_at),
+                _ => None,
+            }
+        };
+
+        if let Some(wait) = wait {
+            tracing::warn!(
+                wait_seconds = wait.as_secs(),
+                "out of GitHub requests. Waiting for the rate limit to reset"
+            );
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// How long to wait before retrying a response that hit a rate limit.
+/// Returns [None] if the response didn't hit a rate limit.
+fn rate_limit_wait(response: &Response) -> Option<Duration> {
+    if !matches!(
+        response.status(),
+        StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS
+    ) {
+        return None;
+    }
+
+    let headers = response.headers();
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    if let Some(retry_after) = header("retry-after").and_then(|value| value.parse().ok()) {
+        return Some(Duration::from_secs(retry_after));
+    }
+
+    if header("x-ratelimit-remaining") == Some("0") {
+        let reset_at = header("x-ratelimit-reset")
+            .and_then(|value| value.parse().ok())
+            .and_then(|reset| OffsetDateTime::from_unix_timestamp(reset).ok())?;
+        // Give GitHub a moment to actually reset the limit
+        return Some(until(reset_at).unwrap_or_default() + Duration::from_secs(1));
+    }
+
+    (response.status() == StatusCode::TOO_MANY_REQUESTS).then_some(SECONDARY_RATE_LIMIT_WAIT)
+}
+
+/// Does the GraphQL response say the query was rejected because we're out of points?
+fn is_graphql_rate_limited(text: &str) -> bool {
+    // Avoid parsing every response twice
+    if !text.contains("RATE_LIMITED") {
+        return false;
+    }
+    serde_json::from_str::<GraphQLResponse<serde::de::IgnoredAny>>(text).is_ok_and(|response| {
+        response
+            .errors
+            .iter()
+            .any(|error| error.error_type.as_deref() == Some("RATE_LIMITED"))
+    })
+}
+
+/// The time left until `instant`, or [None] if it's already passed.
+fn until(instant: OffsetDateTime) -> Option<Duration> {
+    Duration::try_from(instant - OffsetDateTime::now_utc()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delays_grow_exponentially_up_to_the_cap() {
+        let mut policy = RetryPolicy::default();
+        policy
+            .base_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(10));
+        let delays = (0..6)
+            .map(|attempt| policy.jittered_delay(attempt, 0.0).as_secs())
+            .collect::<Vec<_>>();
+        assert_eq!(delays, [1, 2, 4, 8, 10, 10]);
+
+        assert_eq!(policy.jittered_delay(3, 1.0), Duration::from_secs(4));
+        assert_eq!(policy.jittered_delay(5, 1.0), Duration::from_secs(5));
+        // The jitter never takes the delay below the base delay
+        assert_eq!(policy.jittered_delay(0, 1.0), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn retry_delays_stay_between_the_base_and_the_cap() {
+        let base = Duration::from_millis(500);
+        let cap = Duration::from_secs(30);
+        let mut policy = RetryPolicy::new(20);
+        policy.base_delay(base).max_delay(cap);
+        for attempt in 0..40 {
+            for jitter in [0.0, 0.25, 0.5, 0.999, 1.0] {
+                let delay = policy.jittered_delay(attempt, jitter);
+                assert!(
+                    base <= delay && delay <= cap,
+                    "{attempt} {jitter}: {delay:?}"
+                );
+            }
+            let delay = policy.delay(attempt);
+            assert!(base <= delay && delay <= cap, "{attempt}: {delay:?}");
+        }
+    }
+
+    #[test]
+    fn detects_graphql_rate_limit_errors() {
+        let rate_limited =
+            r#"{"errors":[{"type":"RATE_LIMITED","message":"API rate limit exceeded"}]}"#;
+        assert!(is_graphql_rate_limited(rate_limited));
+
+        let not_found = r#"{"data":{"repo0":null},"errors":[{"type":"NOT_FOUND","message":"Could not resolve"}]}"#;
+        assert!(!is_graphql_rate_limited(not_found));
+
+        let data = r#"{"data":{"repo0":{"name":"RATE_LIMITED"}}}"#;
+        assert!(!is_graphql_rate_limited(data));
+    }
+}
@@ -0,0 +1,287 @@
+use super::blocking::BlockingRuntime;
+use super::client::GitHubClient;
+use super::graphql::RUSTFMT_CONFIG_FILE_NAMES;
+use super::nodes::repositories_by_id;
+use super::{github_client, PointBudget, RepoLookupResults, Repository, RetryPolicy, SearchError};
+use serde::Deserialize;
+use std::collections::{HashSet, VecDeque};
+
+/// Details on the endpoint can be found here
+/// <https://docs.github.com/en/rest/search/search#search-code>
+const GITHUB_CODE_SEARCH_URL: &str = "https://api.github.com/search/code";
+
+/// The max number of results GitHub returns on each page of a code search.
+const RESULTS_PER_PAGE: usize = 100;
+
+/// GitHub never returns more than 1000 results for a search.
+const MAX_SEARCH_RESULTS: usize = 1000;
+
+/// Configure searches for repositories that contain a rustfmt config.
+///
+/// Unlike [GitHubRepoSearch](super::GitHubRepoSearch), which relies on repositories being tagged
+/// with the `rust` topic, this uses GitHub code search to find `rustfmt.toml` and
+/// `.rustfmt.toml` files and then looks up the repositories that own them.
+pub struct GitHubCodeSearch<'a> {
+    /// API Key used to authenticate your API calls
+    api_key: &'a str,
+    /// User Agent so GitHub knows which app is making requests
+    user_agent: &'a str,
+    /// Max number of times to query GitHub for a new page of code search results.
+    /// Defaults to 1.
+    max_requests: Option<usize>,
+    /// GraphQL points spent looking up the repositories that own the configs
+    budget: PointBudget,
+    /// How failed requests are retried
+    retry_policy: RetryPolicy,
+}
+
+impl<'a> GitHubCodeSearch<'a> {
+    pub fn new(api_key: &'a str) -> Self {
+        Self {
+            api_key,
+            user_agent: std::env!("GITHUB_USER_AGENT"),
+            max_requests: Some(1),
+            budget: PointBudget::default(),
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Set the max number of code search pages to fetch from GitHub when iterating over
+    /// [CodeSearchResults]. Each page contains up to 100 files.
+    pub fn max_pages(&mut self, max_requests: usize) -> &mut Self {
+        self.max_requests = Some(max_requests);
+        self
+    }
+
+    /// Share a budget of GraphQL points with other requests made during this run.
+    /// Requests stop once the budget is spent.
+    pub fn point_budget(&mut self, budget: &PointBudget) -> &mut Self {
+        self.budget = budget.clone();
+        self
+    }
+
+    /// Set how requests that fail with a timeout or a server error are retried.
+    pub fn retry_policy(&mut self, retry_policy: RetryPolicy) -> &mut Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Build a [CodeSearchResults] object from your configured [GitHubCodeSearch].
+    ///
+    /// **Note**: creating a [CodeSearchResults] does not call the GitHub API.
+    ///
+    /// Returns [SearchError::Auth] if the API key can't be used as a Bearer token.
+    pub fn search(self) -> Result<CodeSearchResults, SearchError> {
+        let mut client = github_client(self.api_key, self.user_agent).ok_or(SearchError::Auth)?;
+        client.set_point_budget(self.budget);
+        client.set_retry_policy(self.retry_policy);
+
+        Ok(CodeSearchResults {
+            client,
+            queries: RUSTFMT_CONFIG_FILE_NAMES
+                .iter()
+                .map(|file_name| format!("filename:{file_name}"))
+                .collect(),
+            page: 1,
+            seen_repositories: HashSet::new(),
+            successful_requests_made: 0,
+            max_requests: self.max_requests,
+            buffered_repos: VecDeque::new(),
+            runtime: BlockingRuntime::default(),
+        })
+    }
+}
+
+impl<'a> IntoIterator for GitHubCodeSearch<'a> {
+    type Item = Repository;
+    type IntoIter = CodeSearchResults;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.search().expect("A valid Bearer token was set")
+    }
+}
+
+/// Used to find repositories that contain a rustfmt config.
+///
+/// You can instantiate [CodeSearchResults] by using [GitHubCodeSearch::search]
+pub struct CodeSearchResults {
+    client: GitHubClient,
+    /// Code search queries that haven't been exhausted yet
+    queries: VecDeque<String>,
+    /// The next page of the current query
+    page: usize,
+    /// GraphQL IDs of repositories that were already returned. A repository can contain
+    /// many configs, but should only be returned once.
+    seen_repositories: HashSet<String>,
+    successful_requests_made: usize,
+    max_requests: Option<usize>,
+    buffered_repos: VecDeque<Repository>,
+    /// Drives [get_next_page](CodeSearchResults::get_next_page) when iterating
+    runtime: BlockingRuntime,
+}
+
+#[derive(Debug, Deserialize)]
+struct CodeSearchResponse {
+    total_count: usize,
+    items: Vec<CodeSearchItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CodeSearchItem {
+    repository: CodeSearchRepository,
+}
+
+#[derive(Debug, Deserialize)]
+struct CodeSearchRepository {
+    /// The GraphQL ID of the repository
+    node_id: String,
+}
+
+impl CodeSearchResults {
+    /// Makes an API call for the next page of code search results and looks up the repositories
+    /// that haven't been returned yet. IDs of repositories that were deleted or made private
+    /// since GitHub indexed them are returned as not found.
+    ///
+    /// [get_next_page](CodeSearchResults::get_next_page) will stop returning results once the
+    /// max_requests pages have been returned, or once all code search results have been seen,
+    /// and return `Ok(None)` from then on.
+    pub async fn get_next_page(&mut self) -> Result<Option<RepoLookupResults>, SearchError> {
+        if let Some(max_requests) = self.max_requests {
+            if max_requests <= self.successful_requests_made {
+                return Ok(None);
+            }
+        }
+
+        let Some(query) = self.queries.front() else {
+            return Ok(None);
+        };
+        let request = self
+            .client
+            .get(GITHUB_CODE_SEARCH_URL)
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            .query(&[
+                ("q", query.as_str()),
+                ("per_page", &RESULTS_PER_PAGE.to_string()),
+                ("page", &self.page.to_string()),
+            ]);
+        let text = self.client.send(request, "code_search").await?;
+
+        tracing::trace!(response_body = text);
+        let response = serde_json::from_str::<CodeSearchResponse>(&text)
+            .map_err(SearchError::Deserialization)?;
+
+        tracing::debug!(query, page = self.page, total_count = response.total_count);
+        self.successful_requests_made += 1;
+        let ids = self.record_page(response);
+
+        repositories_by_id(&self.client, &ids).await.map(Some)
+    }
+
+    /// Move on to the next page, or the next query once this one is exhausted, and return the
+    /// IDs of the repositories on this page that haven't been seen yet.
+    fn record_page(&mut self, response: CodeSearchResponse) -> Vec<String> {
+        let searched = self.page * RESULTS_PER_PAGE;
+        if response.items.is_empty()
+            || searched >= std::cmp::min(response.total_count, MAX_SEARCH_RESULTS)
+        {
+            self.queries.pop_front();
+            self.page = 1;
+        } else {
+            self.page += 1;
+        }
+
+        response
+            .items
+            .into_iter()
+            .map(|item| item.repository.node_id)
+            .filter(|id| self.seen_repositories.insert(id.clone()))
+            .collect()
+    }
+}
+
+impl Iterator for CodeSearchResults {
+    type Item = Repository;
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.buffered_repos.is_empty() {
+            let mut runtime = std::mem::take(&mut self.runtime);
+            let page = runtime.block_on(self.get_next_page());
+            self.runtime = runtime;
+            match page {
+                Ok(results) => {
+                    let results = results?;
+                    for id in results.not_found.iter().chain(&results.unresolved) {
+                        tracing::warn!(id, "could not resolve repository");
+                    }
+                    self.buffered_repos.extend(results.repositories);
+                }
+                Err(err) => {
+                    tracing::error!(search_error = %err, "stopping the code search");
+                    return None;
+                }
+            }
+        }
+        self.buffered_repos.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(total_count: usize, node_ids: &[&str]) -> CodeSearchResponse {
+        CodeSearchResponse {
+            total_count,
+            items: node_ids
+                .iter()
+                .map(|node_id| CodeSearchItem {
+                    repository: CodeSearchRepository {
+                        node_id: node_id.to_string(),
+                    },
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn dedupes_repositories_across_queries() {
+        let mut results = GitHubCodeSearch::new("token").search().unwrap();
+        assert_eq!(results.queries.front().unwrap(), "filename:rustfmt.toml");
+
+        let ids = results.record_page(response(3, &["R_1", "R_2", "R_2", "R_3"]));
+        assert_eq!(ids, ["R_1", "R_2", "R_3"]);
+        assert_eq!(results.queries.front().unwrap(), "filename:.rustfmt.toml");
+        assert_eq!(results.page, 1);
+
+        let ids = results.record_page(response(2, &["R_2", "R_4"]));
+        assert_eq!(ids, ["R_4"]);
+        assert!(results.queries.is_empty());
+    }
+
+    #[test]
+    fn stops_each_query_after_1000_results() {
+        let mut results = GitHubCodeSearch::new("token").search().unwrap();
+        for page in 1..=10 {
+            assert_eq!(results.page, page);
+            assert_eq!(results.queries.len(), 2);
+            let ids = (0..RESULTS_PER_PAGE)
+                .map(|i| format!("R_{page}_{i}"))
+                .collect::<Vec<_>>();
+            let ids = ids.iter().map(String::as_str).collect::<Vec<_>>();
+            results.record_page(response(25_000, &ids));
+        }
+
+        // GitHub won't return an 11th page, so we move on to `.rustfmt.toml`
+        assert_eq!(results.queries.len(), 1);
+        assert_eq!(results.page, 1);
+        assert_eq!(results.seen_repositories.len(), MAX_SEARCH_RESULTS);
+    }
+
+    #[test]
+    fn moves_on_after_an_empty_page() {
+        let mut results = GitHubCodeSearch::new("token").search().unwrap();
+        results.page = 3;
+        assert!(results.record_page(response(500, &[])).is_empty());
+        assert_eq!(results.queries.len(), 1);
+        assert_eq!(results.page, 1);
+    }
+}
@@ -0,0 +1,230 @@
+use super::client::GitHubClient;
+use super::graphql::{github_rustfmt_config_query, GitHubRustfmtConfigResult};
+use super::{github_client, post_graphql, PointBudget, Repository, RetryPolicy, SearchError};
+
+/// Fetches the rustfmt configs of many repositories in as few requests as possible.
+///
+/// ```no_run
+/// # use futures::TryStreamExt;
+/// # use rustfmt_user_config_db::{GitHubRepoSearch, RustfmtConfigFetcher};
+/// # async fn fetch() -> anyhow::Result<()> {
+/// let search = GitHubRepoSearch::new(&"MY_API_TOKEN").search()?;
+/// let repositories = search.into_stream().try_collect::<Vec<_>>().await?;
+/// let fetcher = RustfmtConfigFetcher::new(&"MY_API_TOKEN").unwrap();
+/// for config in fetcher.fetch(&repositories).await?.configs {
+///     println!("{} {}", config.github_graphql_id, config.file_path);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct RustfmtConfigFetcher {
+    client: GitHubClient,
+    /// The number of repositories to look up in a single GraphQL query.
+    /// Defaults to 50
+    batch_size: usize,
+}
+
+/// A rustfmt config file fetched from a repository.
+#[derive(Debug, Clone)]
+pub struct RustfmtConfigFile {
+    /// The GraphQL ID of the repository the config was found in
+    pub github_graphql_id: String,
+    /// The commit the config was read at
+    pub latest_commit: String,
+    /// Path to the config within the repository
+    pub file_path: String,
+    /// The unparsed contents of the config
+    pub contents: String,
+}
+
+/// The rustfmt configs fetched for a set of repositories.
+#[derive(Debug, Default)]
+pub struct FetchedConfigs {
+    /// The configs that were found
+    pub configs: Vec<RustfmtConfigFile>,
+    /// GraphQL IDs of the repositories that were looked up, whether or not they have a rustfmt
+    /// config. Repositories that couldn't be looked up are left out, so their stored configs
+    /// can be kept.
+    pub resolved: Vec<String>,
+}
+
+impl RustfmtConfigFetcher {
+    /// Returns [None] if `api_key` can't be used as a Bearer token.
+    pub fn new(api_key: &str) -> Option<Self> {
+        Some(Self {
+            client: github_client(api_key, std::env!("GITHUB_USER_AGENT"))?,
+            batch_size: 50,
+        })
+    }
+
+    /// Set the number of repositories to look up in each request to GitHub.
+    /// The max value is 100
+    pub fn batch_size(&mut self, batch_size: usize) -> &mut Self {
+        self.batch_size = batch_size.clamp(1, 100);
+        self
+    }
+
+    /// Share a budget of GraphQL points with other requests made during this run.
+    /// Requests fail once the budget is spent.
+    pub fn point_budget(&mut self, budget: &PointBudget) -> &mut Self {
+        self.client.set_point_budget(budget.clone());
+        self
+    }
+
+    /// Set how requests that fail with a timeout or a server error are retried.
+    pub fn retry_policy(&mut self, retry_policy: RetryPolicy) -> &mut Self {
+        self.client.set_retry_policy(retry_policy);
+        self
+    }
+
+    /// Fetch the rustfmt configs found at the root of each repository's latest commit.
+    ///
+    /// Repositories without a rustfmt config, including empty repositories, are skipped.
+    /// Repositories that can't be looked up, either on their own or because their whole batch
+    /// hit a transient failure like a timeout or a rate limit, are logged and left out of
+    /// [FetchedConfigs::resolved].
+    ///
+    /// Other failures, like [SearchError::Auth] or [SearchError::BudgetExhausted], are returned
+    /// since every batch after them would fail too.
+    pub async fn fetch(&self, repositories: &[Repository]) -> Result<FetchedConfigs, SearchError> {
+        let repositories = repositories
+            .iter()
+            .filter_map(|repo| Some((repo, repo.commit_hash()?)))
+            .collect::<Vec<_>>();
+
+        let mut fetched = FetchedConfigs::default();
+        for batch in repositories.chunks(self.batch_size) {
+            match self.fetch_batch(batch, &mut fetched).await {
+                Ok(()) => {}
+                Err(err) if is_transient(&err) => {
+                    tracing::warn!(batch_error=%err, "could not fetch rustfmt configs");
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(fetched)
+    }
+
+    /// Fetch the configs for a batch of repositories along with their latest commit.
+    async fn fetch_batch(
+        &self,
+        repositories: &[(&Repository, &str)],
+        fetched: &mut FetchedConfigs,
+    ) -> Result<(), SearchError> {
+        let query = github_rustfmt_config_query(
+            repositories
+                .iter()
+                .map(|(repo, commit_hash)| (repo.name_with_owner(), *commit_hash)),
+        );
+
+        // Repositories that can't be resolved show up as `null` along with an error,
+        // but the rest of the batch is still usable.
+        let results: GitHubRustfmtConfigResult = post_graphql(
+            &self.client,
+            "GitHubRustfmtConfigs",
+            &query,
+            serde_json::json!({}),
+        )
+        .await?
+        .data;
+
+        collect_configs(repositories, results, fetched);
+        Ok(())
+    }
+}
+
+/// Failures that only cost us the current batch. Its repositories stay queued and are fetched
+/// again on the next run.
+fn is_transient(err: &SearchError) -> bool {
+    err.is_retryable() || matches!(err, SearchError::RateLimited | SearchError::GraphQL(_))
+}
+
+/// Match the `repo{i}` aliases in `results` back up with the repositories they were fetched for.
+fn collect_configs(
+    repositories: &[(&Repository, &str)],
+    mut results: GitHubRustfmtConfigResult,
+    fetched: &mut FetchedConfigs,
+) {
+    for (index, (repo, commit_hash)) in repositories.iter().enumerate() {
+        let Some(Some(blobs)) = results.remove(&format!("repo{index}")) else {
+            tracing::warn!(
+                repo = repo.name_with_owner(),
+                "could not fetch rustfmt config"
+            );
+            continue;
+        };
+
+        fetched.resolved.push(repo.id().to_string());
+        fetched
+            .configs
+            .extend(
+                blobs
+                    .into_configs()
+                    .map(|(file_path, contents)| RustfmtConfigFile {
+                        github_graphql_id: repo.id().to_string(),
+                        latest_commit: commit_hash.to_string(),
+                        file_path: file_path.to_string(),
+                        contents,
+                    }),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github::graphql::tests::repository;
+
+    #[test]
+    fn maps_aliases_back_to_repositories() {
+        let rustfmt = repository("R_rustfmt", "rust-lang/rustfmt", Some("abc123"));
+        let tokio = repository("R_tokio", "tokio-rs/tokio", Some("def456"));
+        let deleted = repository("R_deleted", "someone/deleted", Some("0f0f0f"));
+        let repositories = [
+            (&rustfmt, "abc123"),
+            (&tokio, "def456"),
+            (&deleted, "0f0f0f"),
+        ];
+
+        let results: GitHubRustfmtConfigResult = serde_json::from_value(serde_json::json!({
+            "repo0": {
+                "rustfmtToml": { "text": "edition = \"2021\"\n" },
+                "dotRustfmtToml": null
+            },
+            "repo1": { "rustfmtToml": null, "dotRustfmtToml": null },
+            "repo2": null
+        }))
+        .unwrap();
+
+        let mut fetched = FetchedConfigs::default();
+        collect_configs(&repositories, results, &mut fetched);
+
+        assert_eq!(fetched.resolved, ["R_rustfmt", "R_tokio"]);
+        assert_eq!(fetched.configs.len(), 1);
+        let config = &fetched.configs[0];
+        assert_eq!(config.github_graphql_id, "R_rustfmt");
+        assert_eq!(config.latest_commit, "abc123");
+        assert_eq!(config.file_path, "rustfmt.toml");
+        assert_eq!(config.contents, "edition = \"2021\"\n");
+    }
+
+    #[test]
+    fn only_transient_errors_skip_the_batch() {
+        assert!(is_transient(&SearchError::RateLimited));
+        assert!(is_transient(&SearchError::GraphQL(vec![])));
+        assert!(is_transient(&SearchError::Status {
+            status: reqwest::StatusCode::BAD_GATEWAY,
+            body: String::new(),
+        }));
+        assert!(!is_transient(&SearchError::Auth));
+        assert!(!is_transient(&SearchError::BudgetExhausted {
+            points_spent: 10
+        }));
+        assert!(!is_transient(&SearchError::Status {
+            status: reqwest::StatusCode::FORBIDDEN,
+            body: String::new(),
+        }));
+        let err = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        assert!(!is_transient(&SearchError::Deserialization(err)));
+    }
+}
@@ -0,0 +1,187 @@
+use super::client::MAX_RATE_LIMIT_WAITS;
+use super::graphql::GraphQLError;
+use reqwest::StatusCode;
+use std::fmt::Display;
+
+/// Why a request to GitHub failed.
+#[derive(Debug)]
+pub enum SearchError {
+    /// The API key can't be used as a Bearer token, or GitHub rejected it
+    Auth,
+    /// The request couldn't be sent or the response couldn't be read
+    Transport(reqwest::Error),
+    /// The search was configured with filters that can't match anything,
+    /// like a star range whose minimum is above its maximum
+    InvalidQuery(String),
+    /// GitHub responded with an unsuccessful status code
+    Status { status: StatusCode, body: String },
+    /// We were still rate limited after waiting for the rate limit to reset several times
+    RateLimited,
+    /// Every point in the run's [PointBudget](super::PointBudget) was spent
+    BudgetExhausted { points_spent: u64 },
+    /// There's no user or organization with this login
+    OwnerNotFound(String),
+    /// GitHub couldn't run the GraphQL query
+    GraphQL(Vec<GraphQLError>),
+    /// The response wasn't the JSON we expected
+    Deserialization(serde_json::Error),
+}
+
+impl SearchError {
+    /// Is the failure likely to go away if the request is sent again?
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Transport(err) => err.is_timeout() || err.is_connect() || err.is_body(),
+            Self::Status { status, .. } => status.is_server_error(),
+            _ => false,
+        }
+    }
+
+    /// Did the query take GitHub too long to run? GitHub responds with `502 Bad Gateway` when
+    /// a GraphQL query times out on their end.
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            Self::Transport(err) => err.is_timeout(),
+            Self::Status { status, .. } => {
+                matches!(
+                    *status,
+                    StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT
+                )
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Display for SearchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Auth => write!(f, "GitHub didn't accept the API key"),
+            Self::Transport(err) => write!(f, "failed to reach GitHub: {err}"),
+            Self::InvalidQuery(reason) => write!(f, "invalid search: {reason}"),
+            Self::Status { status, body } => write!(f, "GitHub responded with {status}: {body}"),
+            Self::RateLimited => write!(
+                f,
+                "still rate limited after waiting {MAX_RATE_LIMIT_WAITS} times"
+            ),
+            Self::BudgetExhausted { points_spent } => {
+                write!(f, "spent the whole budget of {points_spent} points")
+            }
+            Self::OwnerNotFound(owner) => {
+                write!(f, "there's no user or organization named `{owner}`")
+            }
+            Self::GraphQL(errors) if errors.is_empty() => {
+                write!(f, "GraphQL query failed without any data")
+            }
+            Self::GraphQL(errors) => {
+                write!(f, "GraphQL query failed: ")?;
+                for (index, error) in errors.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{error}")?;
+                }
+                Ok(())
+            }
+            Self::Deserialization(err) => write!(f, "unexpected response from GitHub: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for SearchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Transport(err) => Some(err),
+            Self::Deserialization(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    fn status(status: StatusCode) -> SearchError {
+        SearchError::Status {
+            status,
+            body: String::new(),
+        }
+    }
+
+    /// Request `url` and return the transport error the request fails with.
+    fn transport_error(url: &str, timeout: Duration) -> SearchError {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let request = async {
+            reqwest::Client::new()
+                .get(url)
+                .timeout(timeout)
+                .send()
+                .await
+        };
+        SearchError::Transport(runtime.block_on(request).unwrap_err())
+    }
+
+    #[test]
+    fn classifies_errors() {
+        // Accepts connections but never responds
+        let silent = TcpListener::bind("127.0.0.1:0").unwrap();
+        let silent_addr = silent.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in silent.incoming() {
+                let mut stream = stream.unwrap();
+                let _ = stream.read(&mut [0; 1024]);
+                std::thread::sleep(Duration::from_secs(5));
+            }
+        });
+        // Refuses connections once it's dropped
+        let closed_addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let timeout = transport_error(&format!("http://{silent_addr}"), Duration::from_millis(100));
+        let connect = transport_error(&format!("http://{closed_addr}"), Duration::from_secs(5));
+        let builder = transport_error("not a url", Duration::from_secs(5));
+        let deserialization =
+            SearchError::Deserialization(serde_json::from_str::<()>("{").unwrap_err());
+
+        // (error, is_retryable, is_timeout)
+        let table = [
+            (SearchError::Auth, false, false),
+            (timeout, true, true),
+            (connect, true, false),
+            (builder, false, false),
+            (SearchError::InvalidQuery("empty".to_string()), false, false),
+            (status(StatusCode::BAD_GATEWAY), true, true),
+            (status(StatusCode::GATEWAY_TIMEOUT), true, true),
+            (status(StatusCode::INTERNAL_SERVER_ERROR), true, false),
+            (status(StatusCode::SERVICE_UNAVAILABLE), true, false),
+            (status(StatusCode::FORBIDDEN), false, false),
+            (status(StatusCode::UNPROCESSABLE_ENTITY), false, false),
+            (SearchError::RateLimited, false, false),
+            (
+                SearchError::BudgetExhausted { points_spent: 10 },
+                false,
+                false,
+            ),
+            (
+                SearchError::OwnerNotFound("nobody".to_string()),
+                false,
+                false,
+            ),
+            (SearchError::GraphQL(vec![]), false, false),
+            (deserialization, false, false),
+        ];
+        for (err, is_retryable, is_timeout) in table {
+            assert_eq!(err.is_retryable(), is_retryable, "is_retryable: {err:?}");
+            assert_eq!(err.is_timeout(), is_timeout, "is_timeout: {err:?}");
+        }
+    }
+}
@@ -1,14 +1,75 @@
+use super::partition::SearchSlice;
+use super::qualifiers::SearchQualifiers;
 use super::search::Repo;
 use super::Repository;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::fmt::Display;
 use time::OffsetDateTime;
 
 /// Details on the endpoint can be found here
 /// <https://docs.github.com/en/graphql/guides/forming-calls-with-graphql#the-graphql-endpoint>
 pub(super) const GITHUB_GRAPHQL_URL: &str = "https://api.github.com/graphql";
 
-pub(super) const GITHUB_REPOSITORY_QUERY: &str = "
+/// The fields we select for every repository, shared between queries so that they can all be
+/// deserialized into a [RepositoryInfo]. Queries that use the fragment must declare a
+/// `$languageOrderBy: LanguageOrder!` variable.
+macro_rules! repository_fields_fragment {
+    () => {
+        r#"
+fragment RepositoryFields on Repository {
+  id
+  nameWithOwner
+  description
+  url
+  archivedAt
+  isFork
+  isLocked
+  pushedAt
+  updatedAt
+  languages(first: 5, orderBy: $languageOrderBy) {
+    totalCount
+    totalSize
+    edges {
+      size
+      node {
+        name
+      }
+    }
+  }
+  defaultBranchRef {
+    target {
+      oid
+    }
+  }
+  clippyToml: object(expression: "HEAD:clippy.toml") {
+    ... on Blob {
+      text
+    }
+  }
+  dotClippyToml: object(expression: "HEAD:.clippy.toml") {
+    ... on Blob {
+      text
+    }
+  }
+  editorconfig: object(expression: "HEAD:.editorconfig") {
+    ... on Blob {
+      text
+    }
+  }
+  preCommitConfig: object(expression: "HEAD:.pre-commit-config.yaml") {
+    ... on Blob {
+      text
+    }
+  }
+}
+"#
+    };
+}
+
+pub(super) const GITHUB_REPOSITORY_QUERY: &str = concat!(
+    r#"
 query GitHubRepositorySearch(
   # The search string to look for. GitHub search syntax is supported.
   $gitHubSearchString: String!
@@ -27,43 +88,178 @@ query GitHubRepositorySearch(
       endCursor
     }
     nodes {
-      ... on Repository {
-        id
-        nameWithOwner
-        description
-        url
-        archivedAt
-        isFork
-        isLocked
-        pushedAt
-        updatedAt
-        languages(first: 5, orderBy: $languageOrderBy) {
-          totalCount
-          totalSize
-          edges {
-            size
-            node {
-              name
-            }
-          }
-        }
-        defaultBranchRef {
-          target {
-            oid
-          }
-        }
+      ...RepositoryFields
+    }
+  }
+  rateLimit {
+    cost
+    remaining
+    resetAt
+  }
+}
+"#,
+    repository_fields_fragment!()
+);
+
+pub(super) const GITHUB_REPOSITORY_NODES_QUERY: &str = concat!(
+    r#"
+query GitHubRepositoryNodes(
+  # The GraphQL IDs of the repositories to look up. At most 100 IDs can be requested at once.
+  $ids: [ID!]!
+  # Ordering options for language connections.
+  $languageOrderBy: LanguageOrder!
+) {
+  nodes(ids: $ids) {
+    ...RepositoryFields
+  }
+  rateLimit {
+    cost
+    remaining
+    resetAt
+  }
+}
+"#,
+    repository_fields_fragment!()
+);
+
+pub(super) const GITHUB_OWNER_REPOSITORIES_QUERY: &str = concat!(
+    r#"
+query GitHubOwnerRepositories(
+  # The login of the user or organization that owns the repositories.
+  $login: String!
+  # Returns the first n elements from the list. At most 100.
+  $limit: Int!
+  # Returns the elements in the list that come after the specified cursor.
+  # Check the `endCursor` on the returned pageInfo
+  $cursorOffset: String
+  # Only return forks when true, or only return non-forks when false. Null returns both.
+  $isFork: Boolean
+  # Ordering options for language connections.
+  $languageOrderBy: LanguageOrder!
+) {
+  repositoryOwner(login: $login) {
+    repositories(
+      first: $limit
+      after: $cursorOffset
+      isFork: $isFork
+      privacy: PUBLIC
+      ownerAffiliations: [OWNER]
+      orderBy: { field: NAME, direction: ASC }
+    ) {
+      totalCount
+      pageInfo {
+        hasNextPage
+        endCursor
+      }
+      nodes {
+        ...RepositoryFields
       }
     }
   }
+  rateLimit {
+    cost
+    remaining
+    resetAt
+  }
+}
+"#,
+    repository_fields_fragment!()
+);
+
+/// The rustfmt config file names we look for at the root of a repository.
+pub(crate) const RUSTFMT_CONFIG_FILE_NAMES: [&str; 2] = ["rustfmt.toml", ".rustfmt.toml"];
+
+/// Selects the cost of a query and how many points we have left. Every query selects it so that
+/// we can keep track of our rate limit.
+const RATE_LIMIT_SELECTION: &str = "  rateLimit { cost remaining resetAt }\n";
+
+/// Build a single GraphQL document that fetches the rustfmt configs for many repositories.
+///
+/// Each repository is looked up with an aliased `repository(owner:, name:)` selection, so the
+/// response for `repositories[i]` can be found under the `repo{i}` key. The configs are read
+/// at the given commit so that they match the `latest_commit` we stored for the repository.
+pub(super) fn github_rustfmt_config_query<'a>(
+    repositories: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> String {
+    let mut query = String::from("query GitHubRustfmtConfigs {\n");
+    for (index, (name_with_owner, commit)) in repositories.into_iter().enumerate() {
+        let (owner, name) = name_with_owner
+            .split_once('/')
+            .unwrap_or(("", name_with_owner));
+        // JSON string literals are also valid GraphQL string literals
+        let owner = serde_json::Value::from(owner);
+        let name = serde_json::Value::from(name);
+        query.push_str(&format!(
+            "  repo{index}: repository(owner: {owner}, name: {name}) {{\n"
+        ));
+        for (field, file_name) in ["rustfmtToml", "dotRustfmtToml"]
+            .into_iter()
+            .zip(RUSTFMT_CONFIG_FILE_NAMES)
+        {
+            query.push_str(&format!(
+                "    {field}: object(expression: \"{commit}:{file_name}\") {{ ... on Blob {{ text }} }}\n"
+            ));
+        }
+        query.push_str("  }\n");
+    }
+    query.push_str(RATE_LIMIT_SELECTION);
+    query.push('}');
+    query
+}
+
+/// Build a single GraphQL document that looks up many repositories by name.
+///
+/// Each repository is looked up with an aliased `repository(owner:, name:)` selection, so the
+/// response for `repositories[i]` can be found under the `repo{i}` key.
+pub(super) fn github_repository_lookup_query<'a>(
+    repositories: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> String {
+    let mut query =
+        String::from("query GitHubRepositoryLookup($languageOrderBy: LanguageOrder!) {\n");
+    for (index, (owner, name)) in repositories.into_iter().enumerate() {
+        // JSON string literals are also valid GraphQL string literals
+        let owner = serde_json::Value::from(owner);
+        let name = serde_json::Value::from(name);
+        query.push_str(&format!(
+            "  repo{index}: repository(owner: {owner}, name: {name}) {{ ...RepositoryFields }}\n"
+        ));
+    }
+    query.push_str(RATE_LIMIT_SELECTION);
+    query.push('}');
+    query.push_str(repository_fields_fragment!());
+    query
+}
+
+pub(super) fn github_repository_lookup_variables() -> serde_json::Value {
+    serde_json::json!({
+      "languageOrderBy": {"field": "SIZE", "direction": "DESC"}
+    })
+}
+
+pub(super) const GITHUB_REPOSITORY_COUNT_QUERY: &str = "
+query GitHubRepositoryCount(
+  # The search string to look for. GitHub search syntax is supported.
+  $gitHubSearchString: String!
+) {
+  search(first: 1, query: $gitHubSearchString, type: REPOSITORY) {
+    repositoryCount
+  }
+  rateLimit {
+    cost
+    remaining
+    resetAt
+  }
 }
 ";
 
-pub(super) fn github_repository_search_variables(
-    limit: usize,
-    cursor_offset: Option<&str>,
-    min_stars: Option<usize>,
+/// Build the GitHub search string used to find repositories.
+///
+/// When searching for a specific repository the `slice` and topics are ignored.
+pub(super) fn github_repository_search_string(
+    slice: &SearchSlice,
+    qualifiers: &SearchQualifiers,
     repo_name: Option<&Repo>,
-) -> serde_json::Value {
+) -> String {
     let mut search_string = match repo_name {
         Some(Repo::Name(name)) => {
             format!("{name} in:name ")
@@ -71,13 +267,23 @@ pub(super) fn github_repository_search_variables(
         Some(Repo::NameWithOwner(name)) => {
             format!("repo:{name} ")
         }
+        None if qualifiers.topics.is_empty() => {
+            format!("{slice} ")
+        }
         None => {
-            format!("topic:rust stars:>={} ", min_stars.unwrap_or(50))
+            format!("{} {slice} ", qualifiers.topic_qualifiers())
         }
     };
 
-    search_string.push_str("language:rust template:false archived:false");
+    search_string.push_str(&qualifiers.to_string());
+    search_string
+}
 
+pub(super) fn github_repository_search_variables(
+    limit: usize,
+    cursor_offset: Option<&str>,
+    search_string: &str,
+) -> serde_json::Value {
     serde_json::json!({
       "gitHubSearchString": search_string,
       "limit": limit,
@@ -86,10 +292,105 @@ pub(super) fn github_repository_search_variables(
     })
 }
 
+pub(super) fn github_repository_count_variables(search_string: &str) -> serde_json::Value {
+    serde_json::json!({
+      "gitHubSearchString": search_string,
+    })
+}
+
+pub(super) fn github_owner_repositories_variables(
+    login: &str,
+    limit: usize,
+    cursor_offset: Option<&str>,
+    include_forks: bool,
+) -> serde_json::Value {
+    serde_json::json!({
+      "login": login,
+      "limit": limit,
+      "cursorOffset": cursor_offset,
+      "isFork": if include_forks { None } else { Some(false) },
+      "languageOrderBy": {"field": "SIZE", "direction": "DESC"}
+    })
+}
+
+pub(super) fn github_repository_nodes_variables(ids: &[String]) -> serde_json::Value {
+    serde_json::json!({
+      "ids": ids,
+      "languageOrderBy": {"field": "SIZE", "direction": "DESC"}
+    })
+}
+
 #[derive(Debug, Deserialize)]
 pub(super) struct GraphQLResponse<T> {
     pub data: Option<T>,
-    pub error: Option<serde_json::Value>,
+    #[serde(default)]
+    pub errors: Vec<GraphQLError>,
+}
+
+/// The `data` of a GraphQL response along with errors for any parts of the query that failed.
+///
+/// GitHub still returns `data` when only some fields can't be resolved, with `null` in place of
+/// those fields.
+#[derive(Debug)]
+pub(super) struct GraphQLData<T> {
+    pub data: T,
+    pub errors: Vec<GraphQLError>,
+}
+
+/// An error GitHub returned for a GraphQL query.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GraphQLError {
+    pub message: String,
+    /// The kind of error, like `NOT_FOUND`
+    #[serde(rename = "type")]
+    pub error_type: Option<String>,
+    /// The fields and list indexes leading to the part of the query that failed
+    #[serde(default)]
+    pub path: Vec<GraphQLPathSegment>,
+}
+
+impl GraphQLError {
+    /// Did GitHub fail to find the object at `path`?
+    pub(super) fn is_not_found_at(&self, path: &[GraphQLPathSegment]) -> bool {
+        self.error_type.as_deref() == Some("NOT_FOUND") && self.path == path
+    }
+}
+
+impl Display for GraphQLError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(error_type) = &self.error_type {
+            write!(f, "{error_type}: ")?;
+        }
+        write!(f, "{}", self.message)?;
+        if !self.path.is_empty() {
+            write!(f, " (at ")?;
+            for (index, segment) in self.path.iter().enumerate() {
+                if index > 0 {
+                    write!(f, ".")?;
+                }
+                write!(f, "{segment}")?;
+            }
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+/// A step in the path to the part of a GraphQL query that failed.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum GraphQLPathSegment {
+    Field(String),
+    Index(usize),
+}
+
+impl Display for GraphQLPathSegment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Field(field) => write!(f, "{field}"),
+            Self::Index(index) => write!(f, "{index}"),
+        }
+    }
 }
 
 impl<T> GraphQLResponse<T>
@@ -101,13 +402,24 @@ where
     }
 }
 
+/// The `rateLimit` selected in every query.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RateLimit {
+    /// The number of points the query cost
+    pub cost: u64,
+    /// The number of points left until the rate limit resets
+    pub remaining: u64,
+    /// When the rate limit resets
+    #[serde(with = "time::serde::iso8601")]
+    pub reset_at: OffsetDateTime,
+}
+
 /// Information about pagination in a connection.
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct PageInfo {
     /// When paginating forwards, are there more items?
-    #[allow(unused)]
-    // FIXME(ytmimi): I'm not sure if `has_next_page` is useful, but i'll keep it for now.
     has_next_page: bool,
     /// When paginating forwards, the cursor to continue
     end_cursor: Option<String>,
@@ -123,13 +435,12 @@ pub(super) struct GitHubSearchResult {
 #[serde(rename_all = "camelCase")]
 struct GitHubSearchResultInner {
     /// The total number of repositories that matched the search query
-    #[allow(dead_code)]
     repository_count: usize,
     /// Information to aid in pagination.
     page_info: PageInfo,
     #[serde(rename = "nodes")]
     /// A list of repositories
-    repositories: Vec<RepositoryInfo>,
+    repositories: RepositoryNodes,
 }
 
 impl GitHubSearchResult {
@@ -140,17 +451,220 @@ impl GitHubSearchResult {
 
     /// Convert the search result into a [Vec<RepositoryInfo>].
     pub(super) fn into_repositories(self) -> Vec<Repository> {
-        self.search
-            .repositories
-            .into_iter()
-            .map(|repo_info| repo_info.into())
-            .collect::<_>()
+        self.search.repositories.into_repositories()
+    }
+
+    /// Take the nodes that couldn't be deserialized into a repository.
+    pub(super) fn take_rejected_nodes(&mut self) -> Vec<RejectedNode> {
+        std::mem::take(&mut self.search.repositories.rejected)
     }
 
     /// Token for the next page of data if it exists.
     pub(super) fn next_page(&self) -> Option<&str> {
         self.search.page_info.end_cursor.as_deref()
     }
+
+    /// Are there more pages of results after this one?
+    pub(super) fn has_next_page(&self) -> bool {
+        self.search.page_info.has_next_page
+    }
+}
+
+/// The result of a [GITHUB_REPOSITORY_COUNT_QUERY].
+#[derive(Debug, Deserialize)]
+pub(super) struct GitHubSearchCount {
+    search: GitHubSearchCountInner,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GitHubSearchCountInner {
+    /// The total number of repositories that matched the search query
+    repository_count: usize,
+}
+
+impl GitHubSearchCount {
+    /// The total number of repositories that met the search critera.
+    pub(super) fn total_repository_count(&self) -> usize {
+        self.search.repository_count
+    }
+}
+
+/// The result of a [GITHUB_OWNER_REPOSITORIES_QUERY].
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct GitHubOwnerRepositoriesResult {
+    /// [None] when no user or organization has the requested login.
+    repository_owner: Option<GitHubOwnerRepositoriesInner>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubOwnerRepositoriesInner {
+    repositories: GitHubRepositoryConnection,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GitHubRepositoryConnection {
+    /// The total number of repositories owned by the user or organization
+    total_count: usize,
+    /// Information to aid in pagination.
+    page_info: PageInfo,
+    #[serde(rename = "nodes")]
+    /// A list of repositories
+    repositories: RepositoryNodes,
+}
+
+impl GitHubOwnerRepositoriesResult {
+    /// Was there a user or organization with the requested login?
+    pub(super) fn owner_exists(&self) -> bool {
+        self.repository_owner.is_some()
+    }
+
+    /// The total number of repositories owned by the user or organization.
+    pub(super) fn total_repository_count(&self) -> usize {
+        self.repository_owner
+            .as_ref()
+            .map_or(0, |owner| owner.repositories.total_count)
+    }
+
+    /// Token for the next page of data if it exists.
+    pub(super) fn next_page(&self) -> Option<&str> {
+        self.repository_owner
+            .as_ref()?
+            .repositories
+            .page_info
+            .end_cursor
+            .as_deref()
+    }
+
+    /// Are there more pages of results after this one?
+    pub(super) fn has_next_page(&self) -> bool {
+        self.repository_owner
+            .as_ref()
+            .is_some_and(|owner| owner.repositories.page_info.has_next_page)
+    }
+
+    /// Convert the result into a [Vec<Repository>].
+    pub(super) fn into_repositories(self) -> Vec<Repository> {
+        self.repository_owner
+            .map(|owner| owner.repositories.repositories.into_repositories())
+            .unwrap_or_default()
+    }
+
+    /// Take the nodes that couldn't be deserialized into a repository.
+    pub(super) fn take_rejected_nodes(&mut self) -> Vec<RejectedNode> {
+        self.repository_owner
+            .as_mut()
+            .map(|owner| std::mem::take(&mut owner.repositories.repositories.rejected))
+            .unwrap_or_default()
+    }
+}
+
+/// A page of repository nodes, deserialized one node at a time so that a single node we can't
+/// make sense of doesn't cost us the rest of the page.
+#[derive(Debug, Default)]
+struct RepositoryNodes {
+    repositories: Vec<RepositoryInfo>,
+    /// Nodes that couldn't be deserialized into a [RepositoryInfo]
+    rejected: Vec<RejectedNode>,
+}
+
+impl RepositoryNodes {
+    fn into_repositories(self) -> Vec<Repository> {
+        self.repositories
+            .into_iter()
+            .map(Repository::from)
+            .collect()
+    }
+}
+
+impl<'de> Deserialize<'de> for RepositoryNodes {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut nodes = RepositoryNodes::default();
+        for node in Vec::<RepositoryNode>::deserialize(deserializer)? {
+            match node {
+                RepositoryNode::Repository(repo_info) => nodes.repositories.push(repo_info),
+                // Nodes that GitHub couldn't resolve are reported as GraphQL errors
+                RepositoryNode::Null => {}
+                RepositoryNode::Rejected(rejected) => nodes.rejected.push(rejected),
+            }
+        }
+        Ok(nodes)
+    }
+}
+
+/// A single repository in a response, deserialized on its own so that a node we can't make
+/// sense of doesn't fail the rest of the response.
+// Most nodes are repositories, so boxing them would only add an allocation per node
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+pub(super) enum RepositoryNode {
+    Repository(RepositoryInfo),
+    /// GitHub couldn't resolve the node, and returned a GraphQL error explaining why
+    Null,
+    /// The node couldn't be deserialized into a [RepositoryInfo]
+    Rejected(RejectedNode),
+}
+
+impl<'de> Deserialize<'de> for RepositoryNode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let node = serde_json::Value::deserialize(deserializer)?;
+        if node.is_null() {
+            return Ok(RepositoryNode::Null);
+        }
+
+        let field = |name| node.get(name).and_then(|v| v.as_str()).map(str::to_string);
+        let id = field("id");
+        let name_with_owner = field("nameWithOwner");
+        match serde_json::from_value::<RepositoryInfo>(node) {
+            Ok(repo_info) => Ok(RepositoryNode::Repository(repo_info)),
+            Err(err) => {
+                tracing::warn!(
+                    id,
+                    repo = name_with_owner,
+                    reason = %err,
+                    "skipping node that isn't a repository we understand"
+                );
+                Ok(RepositoryNode::Rejected(RejectedNode {
+                    id,
+                    name_with_owner,
+                    reason: err.to_string(),
+                }))
+            }
+        }
+    }
+}
+
+/// A search result that couldn't be deserialized into a [Repository].
+#[derive(Debug, Clone)]
+pub struct RejectedNode {
+    /// The node's GraphQL ID, if it had one
+    pub id: Option<String>,
+    /// The repository's name with owner, if the node had one
+    pub name_with_owner: Option<String>,
+    /// Why the node was rejected
+    pub reason: String,
+}
+
+/// The result of a [GITHUB_REPOSITORY_NODES_QUERY].
+#[derive(Debug, Deserialize)]
+pub(super) struct GitHubNodesResult {
+    /// One entry for each requested ID. IDs that couldn't be resolved are `null`.
+    nodes: Vec<RepositoryNode>,
+}
+
+impl GitHubNodesResult {
+    /// The node for each ID, in the same order the IDs were requested.
+    pub(super) fn into_nodes(self) -> impl Iterator<Item = RepositoryNode> {
+        self.nodes.into_iter()
+    }
 }
 
 /// Metadata about this GitHub Repository
@@ -187,7 +701,16 @@ pub(super) struct RepositoryInfo {
     /// A list containing a breakdown of the language composition of the repository.
     languages: Languages,
     /// The Ref associated with the repository's default branch.
-    default_branch_ref: GitBranchRef,
+    /// [None] for empty repositories, which don't have any branches.
+    default_branch_ref: Option<GitBranchRef>,
+    /// `clippy.toml` at the root of the default branch
+    clippy_toml: Option<GitBlob>,
+    /// `.clippy.toml` at the root of the default branch
+    dot_clippy_toml: Option<GitBlob>,
+    /// `.editorconfig` at the root of the default branch
+    editorconfig: Option<GitBlob>,
+    /// `.pre-commit-config.yaml` at the root of the default branch
+    pre_commit_config: Option<GitBlob>,
 }
 
 fn deserialize_git_url<'de, D>(data: D) -> Result<String, D::Error>
@@ -245,8 +768,11 @@ impl RepositoryInfo {
     }
 
     /// Returns a reference to the latest commit hash fetched from GitHub.
-    pub(super) fn commit_hash(&self) -> &str {
-        &self.default_branch_ref.target.oid
+    /// Empty repositories don't have any commits.
+    pub(super) fn commit_hash(&self) -> Option<&str> {
+        self.default_branch_ref
+            .as_ref()
+            .map(|branch| branch.target.oid.as_str())
     }
 
     /// Identifies if the repository is a fork.
@@ -272,6 +798,18 @@ impl RepositoryInfo {
     pub(super) fn updated_at(&self) -> OffsetDateTime {
         self.updated_at
     }
+
+    /// Formatting related config files found at the root of the default branch.
+    pub(super) fn config_files(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        [
+            ("clippy.toml", &self.clippy_toml),
+            (".clippy.toml", &self.dot_clippy_toml),
+            (".editorconfig", &self.editorconfig),
+            (".pre-commit-config.yaml", &self.pre_commit_config),
+        ]
+        .into_iter()
+        .filter_map(|(path, blob)| Some((path, blob.as_ref()?.text.as_deref()?)))
+    }
 }
 
 /// A list of languages associated with the Repository.
@@ -345,3 +883,324 @@ struct GitBranchRef {
 struct GitCommit {
     oid: String,
 }
+
+/// The contents of a file. `text` is [None] for binary files.
+#[derive(Debug, Deserialize)]
+struct GitBlob {
+    text: Option<String>,
+}
+
+/// The result of a [github_repository_lookup_query], keyed by the `repo{i}` alias.
+/// Repositories that don't exist or can't be accessed are [RepositoryNode::Null].
+pub(super) type GitHubRepositoryLookupResult = HashMap<String, RepositoryNode>;
+
+/// The result of a [github_rustfmt_config_query], keyed by the `repo{i}` alias.
+pub(super) type GitHubRustfmtConfigResult = HashMap<String, Option<RustfmtConfigBlobs>>;
+
+/// The rustfmt configs found at the root of a repository.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RustfmtConfigBlobs {
+    rustfmt_toml: Option<GitBlob>,
+    dot_rustfmt_toml: Option<GitBlob>,
+}
+
+impl RustfmtConfigBlobs {
+    /// The file name and contents of each config that was found.
+    pub(super) fn into_configs(self) -> impl Iterator<Item = (&'static str, String)> {
+        RUSTFMT_CONFIG_FILE_NAMES
+            .into_iter()
+            .zip([self.rustfmt_toml, self.dot_rustfmt_toml])
+            .filter_map(|(file_name, blob)| Some((file_name, blob?.text?)))
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::github::SearchError;
+
+    /// A repository node the way GitHub returns it for the `RepositoryFields` fragment.
+    pub(crate) fn repository_node(
+        id: &str,
+        name_with_owner: &str,
+        commit: Option<&str>,
+    ) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "nameWithOwner": name_with_owner,
+            "description": null,
+            "url": format!("https://github.com/{name_with_owner}"),
+            "archivedAt": null,
+            "isFork": false,
+            "isLocked": false,
+            "pushedAt": "2024-06-30T18:04:11Z",
+            "updatedAt": "2024-07-01T09:12:45Z",
+            "languages": {
+                "totalCount": 2,
+                "totalSize": 1000,
+                "edges": [
+                    { "size": 900, "node": { "name": "Rust" } },
+                    { "size": 100, "node": { "name": "Shell" } }
+                ]
+            },
+            "defaultBranchRef": commit.map(|oid| serde_json::json!({ "target": { "oid": oid } })),
+            "clippyToml": null,
+            "dotClippyToml": null,
+            "editorconfig": null,
+            "preCommitConfig": null
+        })
+    }
+
+    /// A [Repository] deserialized from a [repository_node].
+    pub(crate) fn repository(id: &str, name_with_owner: &str, commit: Option<&str>) -> Repository {
+        serde_json::from_value::<RepositoryInfo>(repository_node(id, name_with_owner, commit))
+            .unwrap()
+            .into()
+    }
+
+    /// Deserialize a response the way [post_graphql](crate::github::post_graphql) does.
+    fn graphql_data<T: DeserializeOwned>(text: &str) -> Result<GraphQLData<T>, SearchError> {
+        let client = crate::github::github_client("token", "agent").unwrap();
+        crate::github::graphql_data(&client, "Test", text.to_string())
+    }
+
+    /// A lookup where one of the repositories doesn't exist anymore.
+    const PARTIAL_LOOKUP_RESPONSE: &str = r#"{
+      "data": {
+        "repo0": null,
+        "rateLimit": { "cost": 1, "remaining": 4987, "resetAt": "2024-07-14T18:21:09Z" }
+      },
+      "errors": [
+        {
+          "type": "NOT_FOUND",
+          "path": ["repo0"],
+          "locations": [{ "line": 2, "column": 3 }],
+          "message": "Could not resolve to a Repository with the name 'someone/deleted'."
+        }
+      ]
+    }"#;
+
+    #[test]
+    fn keeps_partial_data_along_with_errors() {
+        let mut response: serde_json::Value =
+            serde_json::from_str(PARTIAL_LOOKUP_RESPONSE).unwrap();
+        response["data"]["repo1"] =
+            repository_node("R_kgDOAbc", "rust-lang/rustfmt", Some("abc123"));
+        let GraphQLData { data, errors } =
+            graphql_data::<GitHubRepositoryLookupResult>(&response.to_string()).unwrap();
+
+        assert!(matches!(data["repo0"], RepositoryNode::Null));
+        assert!(matches!(
+            &data["repo1"],
+            RepositoryNode::Repository(repo_info) if repo_info.id() == "R_kgDOAbc"
+        ));
+        assert!(!data.contains_key("rateLimit"));
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn parses_error_types_and_paths() {
+        let response =
+            GraphQLResponse::<serde_json::Value>::new(PARTIAL_LOOKUP_RESPONSE.to_string()).unwrap();
+        let error = &response.errors[0];
+        assert_eq!(error.error_type.as_deref(), Some("NOT_FOUND"));
+        assert_eq!(error.path, [GraphQLPathSegment::Field("repo0".to_string())]);
+        assert!(error.is_not_found_at(&[GraphQLPathSegment::Field("repo0".to_string())]));
+        assert_eq!(
+            error.to_string(),
+            "NOT_FOUND: Could not resolve to a Repository with the name 'someone/deleted'. (at repo0)"
+        );
+
+        let response = GraphQLResponse::<serde_json::Value>::new(
+            r#"{
+              "data": { "nodes": [null] },
+              "errors": [
+                {
+                  "type": "FORBIDDEN",
+                  "path": ["nodes", 0],
+                  "extensions": { "saml_failure": true },
+                  "locations": [{ "line": 7, "column": 3 }],
+                  "message": "Resource protected by organization SAML enforcement."
+                }
+              ]
+            }"#
+            .to_string(),
+        )
+        .unwrap();
+        let error = &response.errors[0];
+        assert_eq!(error.error_type.as_deref(), Some("FORBIDDEN"));
+        assert_eq!(
+            error.path,
+            [
+                GraphQLPathSegment::Field("nodes".to_string()),
+                GraphQLPathSegment::Index(0)
+            ]
+        );
+        assert!(!error.is_not_found_at(&error.path));
+    }
+
+    #[test]
+    fn errors_without_data_fail_the_query() {
+        // Invalid queries don't have a `data` field at all, or a `type` for their errors
+        let invalid_query = r#"{
+          "errors": [
+            {
+              "path": ["query GitHubRepositorySearch", "search", "first"],
+              "extensions": {
+                "code": "argumentLiteralsIncompatible",
+                "typeName": "Field",
+                "argumentName": "first"
+              },
+              "locations": [{ "line": 3, "column": 3 }],
+              "message": "Argument 'first' on Field 'search' has an invalid value (101). Expected type 'Int'."
+            }
+          ]
+        }"#;
+        let Err(SearchError::GraphQL(errors)) = graphql_data::<GitHubSearchCount>(invalid_query)
+        else {
+            panic!("an invalid query should fail");
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].error_type, None);
+        assert_eq!(
+            errors[0].path[0],
+            GraphQLPathSegment::Field("query GitHubRepositorySearch".to_string())
+        );
+
+        // Queries that time out come back with `null` data
+        let timeout = r#"{
+          "data": null,
+          "errors": [
+            {
+              "message": "Something went wrong while executing your query. This may be the result of a timeout, or it could be a GitHub bug. Please include `8F4C:3E2B:1A2B3C:1B2C3D:66940B2A` when reporting this issue."
+            }
+          ]
+        }"#;
+        let Err(SearchError::GraphQL(errors)) = graphql_data::<GitHubSearchCount>(timeout) else {
+            panic!("a query without data should fail");
+        };
+        assert!(errors[0].path.is_empty());
+    }
+
+    #[test]
+    fn errors_for_required_fields_fail_the_query() {
+        let response = r#"{
+          "data": { "search": null },
+          "errors": [
+            {
+              "type": "SERVICE_UNAVAILABLE",
+              "path": ["search"],
+              "locations": [{ "line": 3, "column": 3 }],
+              "message": "Search is temporarily unavailable."
+            }
+          ]
+        }"#;
+        assert!(matches!(
+            graphql_data::<GitHubSearchCount>(response),
+            Err(SearchError::GraphQL(errors)) if errors.len() == 1
+        ));
+    }
+
+    /// A page of nodes with an empty repository, one missing a field we need, and an
+    /// issue, which doesn't match the `RepositoryFields` fragment.
+    fn mixed_nodes() -> serde_json::Value {
+        let mut missing_field = repository_node("R_missing", "someone/missing", Some("abc123"));
+        missing_field.as_object_mut().unwrap().remove("pushedAt");
+        serde_json::json!([
+            repository_node("R_empty", "someone/empty", None),
+            missing_field,
+            {},
+            null,
+            repository_node("R_rustfmt", "rust-lang/rustfmt", Some("abc123")),
+        ])
+    }
+
+    fn assert_rejected(node: &RejectedNode, id: Option<&str>, name_with_owner: Option<&str>) {
+        assert_eq!(node.id.as_deref(), id);
+        assert_eq!(node.name_with_owner.as_deref(), name_with_owner);
+    }
+
+    #[test]
+    fn repository_nodes_skip_nodes_they_cant_deserialize() {
+        let mut nodes: RepositoryNodes = serde_json::from_value(mixed_nodes()).unwrap();
+        let rejected = std::mem::take(&mut nodes.rejected);
+        let repositories = nodes.into_repositories();
+
+        assert_eq!(repositories.len(), 2);
+        assert_eq!(repositories[0].id(), "R_empty");
+        assert_eq!(repositories[0].commit_hash(), None);
+        assert_eq!(repositories[1].commit_hash(), Some("abc123"));
+
+        assert_eq!(rejected.len(), 2);
+        assert_rejected(&rejected[0], Some("R_missing"), Some("someone/missing"));
+        assert!(rejected[0].reason.contains("pushedAt"));
+        assert_rejected(&rejected[1], None, None);
+    }
+
+    #[test]
+    fn nodes_results_keep_each_node_in_order() {
+        let result: GitHubNodesResult =
+            serde_json::from_value(serde_json::json!({ "nodes": mixed_nodes() })).unwrap();
+        let nodes = result.into_nodes().collect::<Vec<_>>();
+
+        assert_eq!(nodes.len(), 5);
+        assert!(matches!(
+            &nodes[0],
+            RepositoryNode::Repository(repo_info) if repo_info.commit_hash().is_none()
+        ));
+        let RepositoryNode::Rejected(rejected) = &nodes[1] else {
+            panic!("a node without `pushedAt` should be rejected");
+        };
+        assert_rejected(rejected, Some("R_missing"), Some("someone/missing"));
+        let RepositoryNode::Rejected(rejected) = &nodes[2] else {
+            panic!("a node that isn't a repository should be rejected");
+        };
+        assert_rejected(rejected, None, None);
+        assert!(matches!(nodes[3], RepositoryNode::Null));
+        assert!(matches!(nodes[4], RepositoryNode::Repository(_)));
+    }
+
+    #[test]
+    fn lookup_results_keep_each_alias() {
+        let nodes = mixed_nodes();
+        let aliases = nodes
+            .as_array()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (format!("repo{index}"), node.clone()))
+            .collect::<serde_json::Map<_, _>>();
+        let result: GitHubRepositoryLookupResult = serde_json::from_value(aliases.into()).unwrap();
+
+        assert_eq!(result.len(), 5);
+        assert!(matches!(result["repo0"], RepositoryNode::Repository(_)));
+        assert!(matches!(result["repo1"], RepositoryNode::Rejected(_)));
+        assert!(matches!(result["repo2"], RepositoryNode::Rejected(_)));
+        assert!(matches!(result["repo3"], RepositoryNode::Null));
+        assert!(matches!(result["repo4"], RepositoryNode::Repository(_)));
+    }
+
+    #[test]
+    fn rustfmt_config_query_aliases_each_repository() {
+        let query = github_rustfmt_config_query([
+            ("rust-lang/rustfmt", "abc123"),
+            ("tokio-rs/tokio", "def456"),
+        ]);
+
+        assert!(query.starts_with("query GitHubRustfmtConfigs {\n"));
+        assert!(query.contains("  repo0: repository(owner: \"rust-lang\", name: \"rustfmt\") {\n"));
+        assert!(query.contains("    rustfmtToml: object(expression: \"abc123:rustfmt.toml\")"));
+        assert!(query.contains("    dotRustfmtToml: object(expression: \"abc123:.rustfmt.toml\")"));
+        assert!(query.contains("  repo1: repository(owner: \"tokio-rs\", name: \"tokio\") {\n"));
+        assert!(query.contains("    rustfmtToml: object(expression: \"def456:rustfmt.toml\")"));
+        assert!(!query.contains("repo2"));
+        assert!(query.contains("rateLimit { cost remaining resetAt }"));
+    }
+
+    #[test]
+    fn rustfmt_config_query_escapes_names() {
+        let query = github_rustfmt_config_query([("owner/na\"me", "abc123")]);
+        assert!(query.contains("repo0: repository(owner: \"owner\", name: \"na\\\"me\")"));
+    }
+}
@@ -0,0 +1,218 @@
+use super::client::GitHubClient;
+use super::graphql::{
+    github_repository_lookup_query, github_repository_lookup_variables,
+    GitHubRepositoryLookupResult, RejectedNode, RepositoryNode,
+};
+use super::nodes::repositories_by_id;
+use super::{github_client, post_graphql, PointBudget, Repository, RetryPolicy, SearchError};
+use std::collections::HashSet;
+
+/// Looks up repositories by name instead of searching for them.
+///
+/// ```no_run
+/// # use rustfmt_user_config_db::{GitHubRepoLookup, SearchError};
+/// # async fn lookup() -> Result<(), SearchError> {
+/// let lookup = GitHubRepoLookup::new(&"MY_API_TOKEN").unwrap();
+/// let results = lookup
+///     .lookup(["rust-lang/rustfmt", "https://github.com/tokio-rs/tokio"])
+///     .await?;
+/// for unresolved in &results.unresolved {
+///     println!("Could not find {unresolved}");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct GitHubRepoLookup {
+    client: GitHubClient,
+    /// The number of repositories to look up in a single GraphQL query.
+    /// Defaults to 50
+    batch_size: usize,
+}
+
+/// The outcome of a [GitHubRepoLookup].
+#[derive(Debug, Default)]
+pub struct RepoLookupResults {
+    /// Repositories that were found on GitHub. Entries that resolve to the same repository,
+    /// like its name and its URL, only return it once.
+    pub repositories: Vec<Repository>,
+    /// Entries that aren't a repository name or URL, or that don't resolve to a repository
+    pub unresolved: Vec<String>,
+    /// IDs that GitHub says don't exist anymore, because the repository was deleted or made
+    /// private. Only filled in when looking up IDs, other IDs that don't resolve are unresolved.
+    pub not_found: Vec<String>,
+    /// Repositories GitHub returned that couldn't be deserialized, along with the reason why
+    pub rejected: Vec<RejectedNode>,
+}
+
+impl GitHubRepoLookup {
+    /// Returns [None] if `api_key` can't be used as a Bearer token.
+    pub fn new(api_key: &str) -> Option<Self> {
+        Some(Self {
+            client: github_client(api_key, std::env!("GITHUB_USER_AGENT"))?,
+            batch_size: 50,
+        })
+    }
+
+    /// Set the number of repositories to look up in each request to GitHub.
+    /// The max value is 100
+    pub fn batch_size(&mut self, batch_size: usize) -> &mut Self {
+        self.batch_size = batch_size.clamp(1, 100);
+        self
+    }
+
+    /// Share a budget of GraphQL points with other requests made during this run.
+    /// Requests fail once the budget is spent.
+    pub fn point_budget(&mut self, budget: &PointBudget) -> &mut Self {
+        self.client.set_point_budget(budget.clone());
+        self
+    }
+
+    /// Set how requests that fail with a timeout or a server error are retried.
+    pub fn retry_policy(&mut self, retry_policy: RetryPolicy) -> &mut Self {
+        self.client.set_retry_policy(retry_policy);
+        self
+    }
+
+    /// Look up each entry on GitHub. Entries can be an `owner/name`, a GitHub URL like
+    /// `https://github.com/rust-lang/rustfmt`, or a git URL like
+    /// `git@github.com:rust-lang/rustfmt.git`.
+    ///
+    /// Entries that aren't a repository, or that GitHub can't find, are returned as unresolved.
+    /// Requests that fail altogether are returned as an error.
+    pub async fn lookup<'e>(
+        &self,
+        entries: impl IntoIterator<Item = &'e str>,
+    ) -> Result<RepoLookupResults, SearchError> {
+        let mut results = RepoLookupResults::default();
+        let mut found_ids = HashSet::new();
+        let mut names = vec![];
+        for entry in entries {
+            match parse_repo_entry(entry) {
+                Some((owner, name)) => names.push((entry, owner, name)),
+                None => {
+                    tracing::warn!(entry, "not a GitHub repository name or URL");
+                    results.unresolved.push(entry.to_string());
+                }
+            }
+        }
+
+        for batch in names.chunks(self.batch_size) {
+            let query = github_repository_lookup_query(
+                batch.iter().map(|(_, owner, name)| (*owner, *name)),
+            );
+            let mut found: GitHubRepositoryLookupResult = post_graphql(
+                &self.client,
+                "GitHubRepositoryLookup",
+                &query,
+                github_repository_lookup_variables(),
+            )
+            .await?
+            .data;
+
+            for (index, (entry, _, _)) in batch.iter().enumerate() {
+                match found.remove(&format!("repo{index}")) {
+                    Some(RepositoryNode::Repository(repo_info)) => {
+                        let repository: Repository = repo_info.into();
+                        if found_ids.insert(repository.id().to_string()) {
+                            results.repositories.push(repository);
+                        }
+                    }
+                    Some(RepositoryNode::Rejected(rejected)) => results.rejected.push(rejected),
+                    Some(RepositoryNode::Null) | None => results.unresolved.push(entry.to_string()),
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    /// Look up repositories by their GraphQL ID. Unlike names, IDs don't change when a
+    /// repository is renamed or transferred.
+    ///
+    /// IDs of repositories that were deleted or made private are returned as
+    /// [not_found](RepoLookupResults::not_found). IDs that GitHub couldn't resolve for any other
+    /// reason are returned as unresolved.
+    pub async fn lookup_ids(&self, ids: &[String]) -> Result<RepoLookupResults, SearchError> {
+        repositories_by_id(&self.client, ids).await
+    }
+}
+
+/// Get the owner and name out of an `owner/name`, a GitHub URL, or a git URL.
+fn parse_repo_entry(entry: &str) -> Option<(&str, &str)> {
+    let entry = entry.trim();
+    let path = if let Some((_, path)) = entry.split_once("github.com") {
+        // Covers `https://github.com/owner/name` and `git@github.com:owner/name.git`
+        path.strip_prefix(['/', ':'])?
+    } else if entry.contains("://") || entry.contains('@') {
+        return None;
+    } else {
+        entry
+    };
+
+    let mut parts = path.trim_end_matches('/').split('/');
+    let owner = parts.next().filter(|owner| !owner.is_empty())?;
+    let name = parts.next()?;
+    let name = name.strip_suffix(".git").unwrap_or(name);
+    if name.is_empty() {
+        return None;
+    }
+    Some((owner, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_repo_entry;
+
+    #[test]
+    fn parses_owner_and_name() {
+        assert_eq!(
+            parse_repo_entry("rust-lang/rustfmt"),
+            Some(("rust-lang", "rustfmt"))
+        );
+        assert_eq!(
+            parse_repo_entry("  rust-lang/rustfmt/  "),
+            Some(("rust-lang", "rustfmt"))
+        );
+    }
+
+    #[test]
+    fn parses_github_urls() {
+        assert_eq!(
+            parse_repo_entry("https://github.com/tokio-rs/tokio"),
+            Some(("tokio-rs", "tokio"))
+        );
+        assert_eq!(
+            parse_repo_entry("https://github.com/tokio-rs/tokio/tree/master/tokio"),
+            Some(("tokio-rs", "tokio"))
+        );
+    }
+
+    #[test]
+    fn strips_the_git_suffix() {
+        assert_eq!(
+            parse_repo_entry("https://github.com/rust-lang/rustfmt.git"),
+            Some(("rust-lang", "rustfmt"))
+        );
+        assert_eq!(
+            parse_repo_entry("rust-lang/rustfmt.git"),
+            Some(("rust-lang", "rustfmt"))
+        );
+    }
+
+    #[test]
+    fn parses_ssh_git_urls() {
+        assert_eq!(
+            parse_repo_entry("git@github.com:rust-lang/rustfmt.git"),
+            Some(("rust-lang", "rustfmt"))
+        );
+    }
+
+    #[test]
+    fn rejects_entries_that_arent_github_repositories() {
+        assert_eq!(parse_repo_entry("rustfmt"), None);
+        assert_eq!(parse_repo_entry("rust-lang/"), None);
+        assert_eq!(parse_repo_entry("/rustfmt"), None);
+        assert_eq!(parse_repo_entry("https://gitlab.com/owner/name"), None);
+        assert_eq!(parse_repo_entry("git@gitlab.com:owner/name.git"), None);
+        assert_eq!(parse_repo_entry("https://github.com/rust-lang"), None);
+    }
+}
@@ -0,0 +1,129 @@
+use super::client::GitHubClient;
+use super::graphql::{
+    github_repository_nodes_variables, GitHubNodesResult, GraphQLData, GraphQLError,
+    GraphQLPathSegment, RepositoryNode, GITHUB_REPOSITORY_NODES_QUERY,
+};
+use super::{post_graphql, RepoLookupResults, SearchError};
+
+/// The max number of IDs GitHub lets us look up with a single `nodes(ids:)` query.
+const MAX_NODES_PER_QUERY: usize = 100;
+
+/// Look up repositories by their GraphQL ID.
+///
+/// IDs are requested in batches of [MAX_NODES_PER_QUERY]. IDs that GitHub reports as
+/// `NOT_FOUND`, because the repository was deleted or made private, are returned as not found.
+/// Nodes that can't be deserialized into a repository are returned as rejected, and any other ID
+/// that doesn't resolve to a repository is returned as unresolved.
+pub(super) async fn repositories_by_id(
+    client: &GitHubClient,
+    ids: &[String],
+) -> Result<RepoLookupResults, SearchError> {
+    let mut results = RepoLookupResults::default();
+    for batch in ids.chunks(MAX_NODES_PER_QUERY) {
+        let nodes = post_graphql(
+            client,
+            "GitHubRepositoryNodes",
+            GITHUB_REPOSITORY_NODES_QUERY,
+            github_repository_nodes_variables(batch),
+        )
+        .await?;
+        collect_nodes(batch, nodes, &mut results);
+    }
+    Ok(results)
+}
+
+/// Match each node up with the ID it was requested for. Nodes are returned in the same order
+/// as the IDs, and errors point at the node they're about with a `nodes.{index}` path.
+fn collect_nodes(
+    ids: &[String],
+    GraphQLData { data, errors }: GraphQLData<GitHubNodesResult>,
+    results: &mut RepoLookupResults,
+) {
+    for (index, (id, node)) in ids.iter().zip(data.into_nodes()).enumerate() {
+        let path = [
+            GraphQLPathSegment::Field("nodes".to_string()),
+            GraphQLPathSegment::Index(index),
+        ];
+        match node {
+            RepositoryNode::Repository(repo_info) => results.repositories.push(repo_info.into()),
+            RepositoryNode::Rejected(rejected) => results.rejected.push(rejected),
+            RepositoryNode::Null if errors.iter().any(|err| err.is_not_found_at(&path)) => {
+                results.not_found.push(id.clone())
+            }
+            RepositoryNode::Null => {
+                tracing::warn!(id, errors = ?errors_at(&errors, &path), "could not resolve node");
+                results.unresolved.push(id.clone())
+            }
+        }
+    }
+}
+
+/// The errors GitHub returned for the object at `path`.
+fn errors_at<'e>(errors: &'e [GraphQLError], path: &[GraphQLPathSegment]) -> Vec<&'e GraphQLError> {
+    errors.iter().filter(|err| err.path == path).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github::graphql::tests::repository_node;
+
+    fn nodes_response(source: serde_json::Value) -> GraphQLData<GitHubNodesResult> {
+        let errors = serde_json::from_value(source["errors"].clone()).unwrap();
+        let data = serde_json::from_value(source["data"].clone()).unwrap();
+        GraphQLData { data, errors }
+    }
+
+    #[test]
+    fn only_not_found_nodes_are_gone() {
+        let ids = ["R_found", "R_deleted", "R_forbidden", "R_unexplained"].map(String::from);
+        let response = nodes_response(serde_json::json!({
+            "data": {
+                "nodes": [repository_node("R_found", "rust-lang/rustfmt", Some("abc123")), null, null, null]
+            },
+            "errors": [
+                {
+                    "type": "NOT_FOUND",
+                    "path": ["nodes", 1],
+                    "locations": [{ "line": 7, "column": 3 }],
+                    "message": "Could not resolve to a node with the global id of 'R_deleted'"
+                },
+                {
+                    "type": "FORBIDDEN",
+                    "path": ["nodes", 2],
+                    "locations": [{ "line": 7, "column": 3 }],
+                    "message": "Resource protected by organization SAML enforcement."
+                }
+            ]
+        }));
+
+        let mut results = RepoLookupResults::default();
+        collect_nodes(&ids, response, &mut results);
+
+        assert_eq!(results.repositories.len(), 1);
+        assert_eq!(results.repositories[0].id(), "R_found");
+        assert_eq!(results.not_found, ["R_deleted"]);
+        assert_eq!(results.unresolved, ["R_forbidden", "R_unexplained"]);
+    }
+
+    #[test]
+    fn not_found_errors_must_point_at_the_node() {
+        let ids = ["R_0", "R_1"].map(String::from);
+        let response = nodes_response(serde_json::json!({
+            "data": { "nodes": [null, null] },
+            "errors": [
+                {
+                    "type": "NOT_FOUND",
+                    "path": ["nodes", 1, "defaultBranchRef"],
+                    "message": "Could not resolve to a Ref"
+                }
+            ]
+        }));
+
+        let mut results = RepoLookupResults::default();
+        collect_nodes(&ids, response, &mut results);
+
+        assert!(results.not_found.is_empty());
+        assert_eq!(results.unresolved, ["R_0", "R_1"]);
+    }
+}
@@ -0,0 +1,280 @@
+use super::blocking::BlockingRuntime;
+use super::client::GitHubClient;
+use super::graphql::{
+    github_owner_repositories_variables, GitHubOwnerRepositoriesResult, GraphQLData, GraphQLError,
+    RejectedNode, GITHUB_OWNER_REPOSITORIES_QUERY,
+};
+use super::{github_client, post_graphql, PointBudget, Repository, RetryPolicy, SearchError};
+use std::collections::VecDeque;
+
+/// Configure listings of every Rust repository owned by a user or organization.
+///
+/// Unlike [GitHubRepoSearch](super::GitHubRepoSearch), this doesn't depend on how many stars
+/// a repository has or on how it's tagged, so it finds every repository the owner has.
+pub struct GitHubOwnerRepos<'a> {
+    /// API Key used to authenticate your API calls
+    api_key: &'a str,
+    /// User Agent so GitHub knows which app is making requests
+    user_agent: &'a str,
+    /// The login of the user or organization, e.g. `rust-lang`
+    owner: String,
+    /// Include forks in the listing. Defaults to false.
+    include_forks: bool,
+    /// Skip repositories where less than this percent of the code is written in Rust.
+    min_percent_rust: Option<f64>,
+    /// The number of results to return on each page.
+    /// Defaults to 100
+    limit: usize,
+    /// Max number of times to query GitHub for a new page of data.
+    /// Defaults to every page.
+    max_requests: Option<usize>,
+    /// GraphQL points the listing is allowed to spend
+    budget: PointBudget,
+    /// How failed requests are retried
+    retry_policy: RetryPolicy,
+}
+
+impl<'a> GitHubOwnerRepos<'a> {
+    pub fn new(api_key: &'a str, owner: &str) -> Self {
+        Self {
+            api_key,
+            user_agent: std::env!("GITHUB_USER_AGENT"),
+            owner: owner.to_string(),
+            include_forks: false,
+            min_percent_rust: None,
+            limit: 100,
+            max_requests: None,
+            budget: PointBudget::default(),
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Set the number of repositories that should be returned on each request to GitHub.
+    /// The max value is 100
+    pub fn repositories_per_page(&mut self, limit: usize) -> &mut Self {
+        self.limit = limit.clamp(1, 100);
+        self
+    }
+
+    /// Set whether forks should be included in the listing. Defaults to false.
+    pub fn include_forks(&mut self, include_forks: bool) -> &mut Self {
+        self.include_forks = include_forks;
+        self
+    }
+
+    /// Skip repositories where less than `min_percent_rust` percent of the code is written in Rust.
+    /// Repositories without any Rust are always skipped.
+    pub fn min_percent_rust(&mut self, min_percent_rust: f64) -> &mut Self {
+        self.min_percent_rust = Some(min_percent_rust);
+        self
+    }
+
+    /// Set the max number of pages to fetch from GitHub when iterating over [OwnerRepoResults].
+    /// By default every page is fetched.
+    pub fn max_pages(&mut self, max_requests: usize) -> &mut Self {
+        self.max_requests = Some(max_requests);
+        self
+    }
+
+    /// Share a budget of GraphQL points with other requests made during this run.
+    /// Requests stop once the budget is spent.
+    pub fn point_budget(&mut self, budget: &PointBudget) -> &mut Self {
+        self.budget = budget.clone();
+        self
+    }
+
+    /// Set how requests that fail with a timeout or a server error are retried.
+    pub fn retry_policy(&mut self, retry_policy: RetryPolicy) -> &mut Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Build a [OwnerRepoResults] object from your configured [GitHubOwnerRepos].
+    ///
+    /// **Note**: creating a [OwnerRepoResults] does not call the GitHub API.
+    ///
+    /// ```no_run
+    /// # use rustfmt_user_config_db::GitHubOwnerRepos;
+    /// let owner_repos = GitHubOwnerRepos::new(&"MY_API_TOKEN", "rust-lang");
+    /// for repository in owner_repos {
+    ///     println!("{}", repository.name_with_owner());
+    /// }
+    /// ```
+    ///
+    /// Returns [SearchError::Auth] if the API key can't be used as a Bearer token.
+    pub fn list(self) -> Result<OwnerRepoResults, SearchError> {
+        let mut client = github_client(self.api_key, self.user_agent).ok_or(SearchError::Auth)?;
+        client.set_point_budget(self.budget);
+        client.set_retry_policy(self.retry_policy);
+
+        Ok(OwnerRepoResults {
+            client,
+            owner: self.owner,
+            include_forks: self.include_forks,
+            min_percent_rust: self.min_percent_rust,
+            rejected_repositories: 0,
+            graphql_errors: vec![],
+            rejected_nodes: vec![],
+            next_page: None,
+            exhausted: false,
+            limit: self.limit,
+            successful_requests_made: 0,
+            max_requests: self.max_requests,
+            buffered_repos: VecDeque::with_capacity(self.limit),
+            runtime: BlockingRuntime::default(),
+        })
+    }
+}
+
+impl<'a> IntoIterator for GitHubOwnerRepos<'a> {
+    type Item = Repository;
+    type IntoIter = OwnerRepoResults;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.list().expect("A valid Bearer token was set")
+    }
+}
+
+/// Used to list the Rust repositories of a GitHub user or organization.
+///
+/// You can instantiate [OwnerRepoResults] by using [GitHubOwnerRepos::list]
+pub struct OwnerRepoResults {
+    client: GitHubClient,
+    owner: String,
+    include_forks: bool,
+    /// Repositories with less Rust than this are filtered out
+    min_percent_rust: Option<f64>,
+    /// The number of repositories filtered out because they didn't have enough Rust
+    rejected_repositories: usize,
+    /// Errors from responses that only had partial data
+    graphql_errors: Vec<GraphQLError>,
+    /// Repositories that couldn't be deserialized
+    rejected_nodes: Vec<RejectedNode>,
+    next_page: Option<String>,
+    /// Set once the last page of repositories was returned
+    exhausted: bool,
+    limit: usize,
+    successful_requests_made: usize,
+    max_requests: Option<usize>,
+    buffered_repos: VecDeque<Repository>,
+    /// Drives [get_next_page](OwnerRepoResults::get_next_page) when iterating
+    runtime: BlockingRuntime,
+}
+
+impl OwnerRepoResults {
+    /// Returns the token you can use to query the next page of data if there is one.
+    pub fn next_page(&self) -> Option<&str> {
+        self.next_page.as_deref()
+    }
+
+    /// Errors for the parts of each page that GitHub couldn't resolve. The rest of those pages
+    /// were still returned.
+    pub fn graphql_errors(&self) -> &[GraphQLError] {
+        &self.graphql_errors
+    }
+
+    /// Repositories that were skipped because they couldn't be deserialized, along with the
+    /// reason why.
+    pub fn rejected_nodes(&self) -> &[RejectedNode] {
+        &self.rejected_nodes
+    }
+
+    /// The number of repositories that were skipped because they don't contain enough Rust.
+    pub fn rejected_repositories(&self) -> usize {
+        self.rejected_repositories
+    }
+
+    /// Makes an API call for the next page of the owner's repositories.
+    ///
+    /// Each page will contain up to *`n`* repositories, where *`n`* is configured using
+    /// [repositories_per_page](GitHubOwnerRepos::repositories_per_page). Pages can be empty
+    /// when none of their repositories are written in Rust.
+    ///
+    /// [get_next_page](OwnerRepoResults::get_next_page) will stop returning results once
+    /// every repository was listed, or once the max_requests pages have been returned, and
+    /// return `Ok(None)` from then on. Returns [SearchError::OwnerNotFound] if there's no user
+    /// or organization with the owner's login.
+    pub async fn get_next_page(&mut self) -> Result<Option<Vec<Repository>>, SearchError> {
+        if self.exhausted {
+            return Ok(None);
+        }
+
+        if let Some(max_requests) = self.max_requests {
+            if max_requests <= self.successful_requests_made {
+                return Ok(None);
+            }
+        }
+
+        let variables = github_owner_repositories_variables(
+            &self.owner,
+            self.limit,
+            self.next_page(),
+            self.include_forks,
+        );
+        let GraphQLData {
+            data: mut results,
+            errors,
+        }: GraphQLData<GitHubOwnerRepositoriesResult> = post_graphql(
+            &self.client,
+            "GitHubOwnerRepositories",
+            GITHUB_OWNER_REPOSITORIES_QUERY,
+            variables,
+        )
+        .await?;
+
+        if !results.owner_exists() {
+            return Err(SearchError::OwnerNotFound(self.owner.clone()));
+        }
+
+        let total_repository_count = results.total_repository_count();
+        tracing::debug!(owner = self.owner, total_repository_count);
+        self.graphql_errors.extend(errors);
+        self.rejected_nodes.extend(results.take_rejected_nodes());
+
+        self.exhausted = !results.has_next_page();
+        self.next_page = results.next_page().map(str::to_string);
+        self.successful_requests_made += 1;
+
+        let min_percent_rust = self.min_percent_rust.unwrap_or(0.0);
+        Ok(Some(
+            results
+                .into_repositories()
+                .into_iter()
+                .filter(|repo| {
+                    let percent_of_code_in_rust = repo.percent_of_code_in_rust();
+                    if percent_of_code_in_rust > 0.0 && percent_of_code_in_rust >= min_percent_rust
+                    {
+                        return true;
+                    }
+                    tracing::debug!(
+                        repo = repo.name_with_owner(),
+                        percent_of_code_in_rust,
+                        "not enough rust"
+                    );
+                    self.rejected_repositories += 1;
+                    false
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl Iterator for OwnerRepoResults {
+    type Item = Repository;
+    fn next(&mut self) -> Option<Self::Item> {
+        // Pages can be empty when none of their repositories are written in Rust
+        while self.buffered_repos.is_empty() {
+            let mut runtime = std::mem::take(&mut self.runtime);
+            let page = runtime.block_on(self.get_next_page());
+            self.runtime = runtime;
+            match page {
+                Ok(repositories) => self.buffered_repos.extend(repositories?),
+                Err(err) => {
+                    tracing::error!(search_error = %err, "stopping the listing");
+                    return None;
+                }
+            }
+        }
+        self.buffered_repos.pop_front()
+    }
+}
@@ -0,0 +1,375 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use time::{Date, Month, OffsetDateTime};
+
+/// GitHub never returns more than 1000 results for a search, no matter how many pages we request.
+pub(super) const MAX_SEARCH_RESULTS: usize = 1000;
+
+/// An inclusive range of star counts. `max` is [None] when there's no upper bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct StarRange {
+    pub(super) min: usize,
+    pub(super) max: Option<usize>,
+}
+
+/// An inclusive range of dates. [None] leaves that end of the range open.
+///
+/// Open ends stay open in the search string, so that the same search has the same query no
+/// matter which day it runs on. They're only filled in when the range needs to be split.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct DateRange {
+    pub(super) start: Option<Date>,
+    pub(super) end: Option<Date>,
+}
+
+/// A slice of the search space that can be searched on its own.
+///
+/// Searches that match more than [MAX_SEARCH_RESULTS] repositories are split into smaller slices
+/// until every slice can be fully paginated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct SearchSlice {
+    pub(super) stars: StarRange,
+    pub(super) created: Option<DateRange>,
+    pub(super) pushed: Option<DateRange>,
+    /// Only search repositories tagged with this topic, when searching several topics at once.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) topic: Option<String>,
+}
+
+impl SearchSlice {
+    /// A slice covering every repository within the given star range and date windows.
+    pub(super) fn new(
+        stars: StarRange,
+        created: Option<DateRange>,
+        pushed: Option<DateRange>,
+    ) -> Self {
+        Self {
+            stars,
+            created,
+            pushed,
+            topic: None,
+        }
+    }
+
+    /// The same slice limited to repositories tagged with `topic`.
+    pub(super) fn with_topic(&self, topic: &str) -> Self {
+        Self {
+            topic: Some(topic.to_string()),
+            ..self.clone()
+        }
+    }
+
+    /// Split the slice into two slices that together cover the same repositories.
+    ///
+    /// Star ranges are split first since that's where repositories are least evenly distributed,
+    /// then the creation date, and finally the last pushed date.
+    /// Returns [None] once the slice can't be split any further.
+    pub(super) fn split(&self) -> Option<(SearchSlice, SearchSlice)> {
+        if let Some((low, high)) = self.stars.split() {
+            return Some((
+                SearchSlice {
+                    stars: low,
+                    ..self.clone()
+                },
+                SearchSlice {
+                    stars: high,
+                    ..self.clone()
+                },
+            ));
+        }
+
+        if let Some((earlier, later)) = self.created.unwrap_or_default().split() {
+            return Some((
+                SearchSlice {
+                    created: Some(earlier),
+                    ..self.clone()
+                },
+                SearchSlice {
+                    created: Some(later),
+                    ..self.clone()
+                },
+            ));
+        }
+
+        let (earlier, later) = self.pushed.unwrap_or_default().split()?;
+        Some((
+            SearchSlice {
+                pushed: Some(earlier),
+                ..self.clone()
+            },
+            SearchSlice {
+                pushed: Some(later),
+                ..self.clone()
+            },
+        ))
+    }
+}
+
+impl Display for SearchSlice {
+    /// Format the slice as GitHub search qualifiers, e.g. `stars:50..99 created:2020-01-01..2020-06-30`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.stars.max {
+            Some(max) => write!(f, "stars:{}..{max}", self.stars.min)?,
+            None => write!(f, "stars:>={}", self.stars.min)?,
+        }
+        if let Some(created) = self.created.filter(|created| !created.is_unbounded()) {
+            write!(f, " created:{created}")?;
+        }
+        if let Some(pushed) = self.pushed.filter(|pushed| !pushed.is_unbounded()) {
+            write!(f, " pushed:{pushed}")?;
+        }
+        if let Some(topic) = &self.topic {
+            write!(f, " topic:{topic}")?;
+        }
+        Ok(())
+    }
+}
+
+impl StarRange {
+    fn split(&self) -> Option<(StarRange, StarRange)> {
+        let mid = match self.max {
+            Some(max) if max <= self.min => return None,
+            Some(max) => self.min + (max - self.min) / 2,
+            // Most repositories have few stars, so grow unbounded ranges geometrically
+            None => std::cmp::max(self.min, 1) * 2 - 1,
+        };
+        Some((
+            StarRange {
+                min: self.min,
+                max: Some(mid),
+            },
+            StarRange {
+                min: mid + 1,
+                max: self.max,
+            },
+        ))
+    }
+}
+
+impl DateRange {
+    /// A range between `start` and `end`. [None] leaves that end of the range open.
+    pub(super) fn new(start: Option<Date>, end: Option<Date>) -> Self {
+        Self { start, end }
+    }
+
+    /// Is the range open on both ends, so that it matches every date?
+    fn is_unbounded(&self) -> bool {
+        self.start.is_none() && self.end.is_none()
+    }
+
+    fn split(&self) -> Option<(DateRange, DateRange)> {
+        self.split_until(OffsetDateTime::now_utc().date())
+    }
+
+    /// Split the range at its midpoint, treating an open start as GitHub's launch and an open
+    /// end as `today`. The halves keep the open ends, so the later half still covers
+    /// repositories created or pushed to after `today`.
+    fn split_until(&self, today: Date) -> Option<(DateRange, DateRange)> {
+        let github_launch = Date::from_calendar_date(2008, Month::January, 1).expect("valid date");
+        let start = self.start.unwrap_or(github_launch);
+        let end = self.end.unwrap_or(today);
+        let days = (end - start).whole_days();
+        if days < 1 {
+            return None;
+        }
+        let mid = start + time::Duration::days(days / 2);
+        Some((
+            DateRange {
+                start: self.start,
+                end: Some(mid),
+            },
+            DateRange {
+                start: Some(mid.next_day()?),
+                end: self.end,
+            },
+        ))
+    }
+}
+
+impl Display for DateRange {
+    /// Format the range as a GitHub search range, e.g. `2020-01-01..2020-06-30` or `>=2020-01-01`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) => write!(f, "{start}..{end}"),
+            (Some(start), None) => write!(f, ">={start}"),
+            (None, Some(end)) => write!(f, "<={end}"),
+            (None, None) => write!(f, "*"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stars(min: usize, max: Option<usize>) -> StarRange {
+        StarRange { min, max }
+    }
+
+    fn day(year: i32, month: u8, day: u8) -> Date {
+        Date::from_calendar_date(year, Month::try_from(month).unwrap(), day).unwrap()
+    }
+
+    fn dates(start: Date, end: Date) -> DateRange {
+        DateRange::new(Some(start), Some(end))
+    }
+
+    #[test]
+    fn splits_bounded_star_ranges_in_half() {
+        assert_eq!(
+            stars(0, Some(100)).split(),
+            Some((stars(0, Some(50)), stars(51, Some(100))))
+        );
+        assert_eq!(
+            stars(50, Some(51)).split(),
+            Some((stars(50, Some(50)), stars(51, Some(51))))
+        );
+    }
+
+    #[test]
+    fn splits_unbounded_star_ranges_geometrically() {
+        assert_eq!(
+            stars(0, None).split(),
+            Some((stars(0, Some(1)), stars(2, None)))
+        );
+        assert_eq!(
+            stars(2, None).split(),
+            Some((stars(2, Some(3)), stars(4, None)))
+        );
+        assert_eq!(
+            stars(50, None).split(),
+            Some((stars(50, Some(99)), stars(100, None)))
+        );
+    }
+
+    #[test]
+    fn cant_split_a_single_star_count() {
+        assert_eq!(stars(7, Some(7)).split(), None);
+    }
+
+    #[test]
+    fn splits_date_ranges_by_day() {
+        assert_eq!(
+            dates(day(2020, 1, 1), day(2020, 1, 10)).split(),
+            Some((
+                dates(day(2020, 1, 1), day(2020, 1, 5)),
+                dates(day(2020, 1, 6), day(2020, 1, 10))
+            ))
+        );
+        assert_eq!(
+            dates(day(2020, 1, 1), day(2020, 1, 2)).split(),
+            Some((
+                dates(day(2020, 1, 1), day(2020, 1, 1)),
+                dates(day(2020, 1, 2), day(2020, 1, 2))
+            ))
+        );
+    }
+
+    #[test]
+    fn cant_split_a_single_day() {
+        assert_eq!(dates(day(2020, 1, 1), day(2020, 1, 1)).split(), None);
+    }
+
+    #[test]
+    fn splits_open_date_ranges_until_today() {
+        let today = day(2024, 7, 14);
+        let (earlier, later) = DateRange::new(Some(day(2024, 7, 1)), None)
+            .split_until(today)
+            .unwrap();
+        assert_eq!(earlier, dates(day(2024, 7, 1), day(2024, 7, 7)));
+        // The later half stays open, so it still covers anything after today
+        assert_eq!(later, DateRange::new(Some(day(2024, 7, 8)), None));
+
+        let (earlier, later) = DateRange::default().split_until(today).unwrap();
+        assert_eq!(earlier.start, None);
+        assert_eq!(later.end, None);
+        assert_eq!(earlier.end.unwrap().next_day(), later.start);
+
+        assert_eq!(DateRange::new(Some(today), None).split_until(today), None);
+    }
+
+    #[test]
+    fn splits_stars_then_created_then_pushed() {
+        let one_day = dates(day(2020, 1, 1), day(2020, 1, 1));
+        let days = dates(day(2021, 3, 1), day(2021, 3, 2));
+
+        let slice = SearchSlice::new(stars(0, Some(10)), Some(days), Some(days));
+        let (low, high) = slice.split().unwrap();
+        assert_eq!(
+            (low.stars, high.stars),
+            (stars(0, Some(5)), stars(6, Some(10)))
+        );
+        assert_eq!((low.created, low.pushed), (Some(days), Some(days)));
+
+        let slice = SearchSlice::new(stars(10, Some(10)), Some(days), Some(days));
+        let (earlier, later) = slice.split().unwrap();
+        assert_eq!(earlier.created.unwrap().end, Some(day(2021, 3, 1)));
+        assert_eq!(later.created.unwrap().start, Some(day(2021, 3, 2)));
+        assert_eq!((earlier.stars, earlier.pushed), (slice.stars, Some(days)));
+
+        let slice = SearchSlice::new(stars(10, Some(10)), Some(one_day), Some(days));
+        let (earlier, later) = slice.split().unwrap();
+        assert_eq!(earlier.pushed.unwrap().end, Some(day(2021, 3, 1)));
+        assert_eq!(later.pushed.unwrap().start, Some(day(2021, 3, 2)));
+        assert_eq!(
+            (earlier.stars, earlier.created),
+            (slice.stars, Some(one_day))
+        );
+
+        let slice = SearchSlice::new(stars(10, Some(10)), Some(one_day), Some(one_day));
+        assert_eq!(slice.split(), None);
+    }
+
+    #[test]
+    fn splits_keep_the_topic() {
+        let slice = SearchSlice::new(stars(0, None), None, None).with_topic("cli");
+        let (low, high) = slice.split().unwrap();
+        assert_eq!(low.topic.as_deref(), Some("cli"));
+        assert_eq!(high.topic.as_deref(), Some("cli"));
+    }
+
+    #[test]
+    fn formats_open_date_ranges_without_today() {
+        let start = day(2020, 1, 1);
+        assert_eq!(
+            DateRange::new(Some(start), None).to_string(),
+            ">=2020-01-01"
+        );
+        assert_eq!(
+            DateRange::new(None, Some(start)).to_string(),
+            "<=2020-01-01"
+        );
+        assert_eq!(DateRange::default().to_string(), "*");
+
+        let slice = SearchSlice::new(
+            stars(50, None),
+            Some(DateRange::new(Some(start), None)),
+            Some(DateRange::default()),
+        );
+        assert_eq!(slice.to_string(), "stars:>=50 created:>=2020-01-01");
+    }
+
+    #[test]
+    fn formats_slices_as_search_qualifiers() {
+        assert_eq!(
+            SearchSlice::new(stars(50, None), None, None).to_string(),
+            "stars:>=50"
+        );
+        assert_eq!(
+            SearchSlice::new(stars(50, Some(99)), None, None).to_string(),
+            "stars:50..99"
+        );
+
+        let created = dates(day(2020, 1, 1), day(2020, 6, 30));
+        let pushed = dates(day(2024, 1, 1), day(2024, 1, 31));
+        let slice = SearchSlice::new(stars(0, Some(10)), Some(created), Some(pushed));
+        assert_eq!(
+            slice.to_string(),
+            "stars:0..10 created:2020-01-01..2020-06-30 pushed:2024-01-01..2024-01-31"
+        );
+        assert_eq!(
+            slice.with_topic("cli").to_string(),
+            "stars:0..10 created:2020-01-01..2020-06-30 pushed:2024-01-01..2024-01-31 topic:cli"
+        );
+    }
+}
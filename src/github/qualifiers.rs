@@ -0,0 +1,116 @@
+use std::fmt::Display;
+
+/// GitHub search qualifiers that stay the same for every slice of a partitioned search.
+///
+/// Star ranges and date windows are part of the [SearchSlice](super::partition::SearchSlice)
+/// since those are what a search gets partitioned by.
+#[derive(Debug, Clone)]
+pub(super) struct SearchQualifiers {
+    /// Repositories must be tagged with every one of these topics.
+    /// No topic filter is applied when empty.
+    pub(super) topics: Vec<String>,
+    /// Repositories must be tagged with at least one of these topics.
+    /// Each topic is searched in its own slice, see [SearchSlice::with_topic].
+    pub(super) any_topics: Vec<String>,
+    /// Include forks in the search results
+    pub(super) include_forks: bool,
+    /// Include template repositories in the search results
+    pub(super) include_templates: bool,
+    /// Include archived repositories in the search results
+    pub(super) include_archived: bool,
+    /// Only search repositories owned by this user or organization
+    pub(super) owner: Option<String>,
+    /// Extra qualifiers that are added to the search string as is
+    pub(super) extra: Option<String>,
+}
+
+impl Default for SearchQualifiers {
+    fn default() -> Self {
+        Self {
+            topics: vec!["rust".to_string()],
+            any_topics: vec![],
+            include_forks: false,
+            include_templates: false,
+            include_archived: false,
+            owner: None,
+            extra: None,
+        }
+    }
+}
+
+impl SearchQualifiers {
+    /// Format the topics as GitHub search qualifiers, e.g. `topic:rust topic:cli`
+    pub(super) fn topic_qualifiers(&self) -> String {
+        self.topics
+            .iter()
+            .map(|topic| format!("topic:{topic}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl Display for SearchQualifiers {
+    /// Format every qualifier except for the topics as a GitHub search string,
+    /// e.g. `language:rust template:false archived:false`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(owner) = &self.owner {
+            write!(f, "user:{owner} ")?;
+        }
+        write!(f, "language:rust")?;
+        if self.include_forks {
+            write!(f, " fork:true")?;
+        }
+        if !self.include_templates {
+            write!(f, " template:false")?;
+        }
+        if !self.include_archived {
+            write!(f, " archived:false")?;
+        }
+        if let Some(extra) = &self.extra {
+            write!(f, " {extra}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_default_qualifiers() {
+        let qualifiers = SearchQualifiers::default();
+        assert_eq!(
+            qualifiers.to_string(),
+            "language:rust template:false archived:false"
+        );
+        assert_eq!(qualifiers.topic_qualifiers(), "topic:rust");
+    }
+
+    #[test]
+    fn formats_every_qualifier() {
+        let qualifiers = SearchQualifiers {
+            topics: vec!["rust".to_string(), "cli".to_string()],
+            any_topics: vec!["wasm".to_string()],
+            include_forks: true,
+            include_templates: true,
+            include_archived: true,
+            owner: Some("rust-lang".to_string()),
+            extra: Some("license:mit size:<1000".to_string()),
+        };
+        assert_eq!(
+            qualifiers.to_string(),
+            "user:rust-lang language:rust fork:true license:mit size:<1000"
+        );
+        assert_eq!(qualifiers.topic_qualifiers(), "topic:rust topic:cli");
+    }
+
+    #[test]
+    fn formats_without_topics() {
+        let qualifiers = SearchQualifiers {
+            topics: vec![],
+            ..SearchQualifiers::default()
+        };
+        assert_eq!(qualifiers.topic_qualifiers(), "");
+    }
+}
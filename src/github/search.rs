@@ -1,13 +1,23 @@
+use super::blocking::BlockingRuntime;
+use super::client::GitHubClient;
 use super::graphql::{
-    github_repository_search_variables, GitHubSearchResult, GraphQLResponse, GITHUB_GRAPHQL_URL,
-    GITHUB_REPOSITORY_QUERY,
+    github_repository_count_variables, github_repository_search_string,
+    github_repository_search_variables, GitHubSearchCount, GitHubSearchResult, GraphQLData,
+    GraphQLError, RejectedNode, GITHUB_REPOSITORY_COUNT_QUERY, GITHUB_REPOSITORY_QUERY,
 };
-use super::Repository;
-use reqwest::header;
-use std::collections::VecDeque;
+use super::partition::{DateRange, SearchSlice, StarRange, MAX_SEARCH_RESULTS};
+use super::qualifiers::SearchQualifiers;
+use super::{github_client, post_graphql, PointBudget, Repository, RetryPolicy, SearchError};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
 use std::convert::Infallible;
 use std::ops::Deref;
 use std::str::FromStr;
+use time::Date;
+
+/// The smallest page size used when shrinking pages after a search times out.
+const MIN_PAGE_SIZE: usize = 10;
 
 /// Configure searches for GitHub repositories.
 pub struct GitHubRepoSearch<'a> {
@@ -18,6 +28,16 @@ pub struct GitHubRepoSearch<'a> {
     /// Filter GitHub search results for repositories with this number of stars or higher.
     /// Defaults to 50
     min_stars: usize,
+    /// Filter GitHub search results for repositories with this number of stars or lower.
+    max_stars: Option<usize>,
+    /// Only include repositories created within this window.
+    created: Option<DateRange>,
+    /// Only include repositories last pushed to within this window.
+    pushed: Option<DateRange>,
+    /// Other qualifiers used to filter the search results.
+    qualifiers: SearchQualifiers,
+    /// Skip repositories where less than this percent of the code is written in Rust.
+    min_percent_rust: Option<f64>,
     /// The number of results to return on each page.
     /// Defaults to 100
     limit: usize,
@@ -26,6 +46,45 @@ pub struct GitHubRepoSearch<'a> {
     max_requests: Option<usize>,
     /// Name of the repository to search for
     repo_name: Option<Repo>,
+    /// Split searches that match more than 1000 repositories into smaller searches.
+    /// Defaults to true.
+    partition_queries: bool,
+    /// GraphQL points this search is allowed to spend
+    budget: PointBudget,
+    /// How failed requests are retried
+    retry_policy: RetryPolicy,
+    /// Where to pick up a search that was stopped before it finished
+    resume: Option<Resume>,
+}
+
+/// Where to pick up a search that was stopped before it finished.
+enum Resume {
+    /// Continue paginating the whole search after this cursor
+    After(String),
+    /// Continue from a checkpoint taken by [RepoSearchResults::checkpoint]
+    Checkpoint(SearchCheckpoint),
+}
+
+/// Where a search left off, so that it can be resumed later with
+/// [resume_from](GitHubRepoSearch::resume_from).
+///
+/// Checkpoints can be serialized to store them between runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchCheckpoint {
+    /// The slice that was being paginated
+    current_slice: Option<SearchSlice>,
+    /// The cursor to continue `current_slice` from
+    cursor: Option<String>,
+    /// Slices that still need to be searched
+    pending_slices: Vec<SearchSlice>,
+}
+
+impl SearchCheckpoint {
+    /// The cursor of the last page that was returned, if the search was in the middle of
+    /// paginating a slice.
+    pub fn cursor(&self) -> Option<&str> {
+        self.cursor.as_deref()
+    }
 }
 
 impl<'a> GitHubRepoSearch<'a> {
@@ -34,9 +93,18 @@ impl<'a> GitHubRepoSearch<'a> {
             api_key,
             user_agent: std::env!("GITHUB_USER_AGENT"),
             min_stars: 50,
+            max_stars: None,
+            created: None,
+            pushed: None,
+            qualifiers: SearchQualifiers::default(),
+            min_percent_rust: None,
             limit: 100,
             max_requests: Some(1),
             repo_name: None,
+            partition_queries: true,
+            budget: PointBudget::default(),
+            retry_policy: RetryPolicy::default(),
+            resume: None,
         }
     }
 
@@ -53,12 +121,196 @@ impl<'a> GitHubRepoSearch<'a> {
         self
     }
 
+    /// Set the maximum number of stars used to filter repositories
+    pub fn max_stars(&mut self, max_stars: usize) -> &mut Self {
+        self.max_stars = Some(max_stars);
+        self
+    }
+
+    /// Set the topics used to filter repositories. Repositories must be tagged with every topic.
+    /// Pass an empty list to search repositories regardless of their topics.
+    /// Defaults to `rust`.
+    ///
+    /// Use [any_topics](GitHubRepoSearch::any_topics) to search for repositories tagged with
+    /// any one of several topics.
+    pub fn topics<I, S>(&mut self, topics: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.qualifiers.topics = topics.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Only search for repositories tagged with at least one of these topics, on top of the
+    /// [topics](GitHubRepoSearch::topics) they must all be tagged with.
+    ///
+    /// GitHub can't search for one topic or another, so each topic is searched separately and
+    /// repositories found more than once are only returned the first time.
+    pub fn any_topics<I, S>(&mut self, topics: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.qualifiers.any_topics = topics.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Only search for repositories created between `start` and `end` (inclusive).
+    /// [None] leaves that end of the window open.
+    pub fn created_between(&mut self, start: Option<Date>, end: Option<Date>) -> &mut Self {
+        self.created = Some(DateRange::new(start, end));
+        self
+    }
+
+    /// Only search for repositories last pushed to between `start` and `end` (inclusive).
+    /// [None] leaves that end of the window open.
+    pub fn pushed_between(&mut self, start: Option<Date>, end: Option<Date>) -> &mut Self {
+        self.pushed = Some(DateRange::new(start, end));
+        self
+    }
+
+    /// Set whether forks should be included in the search results. Defaults to false.
+    pub fn include_forks(&mut self, include_forks: bool) -> &mut Self {
+        self.qualifiers.include_forks = include_forks;
+        self
+    }
+
+    /// Set whether template repositories should be included in the search results.
+    /// Defaults to false.
+    pub fn include_templates(&mut self, include_templates: bool) -> &mut Self {
+        self.qualifiers.include_templates = include_templates;
+        self
+    }
+
+    /// Set whether archived repositories should be included in the search results.
+    /// Defaults to false.
+    pub fn include_archived(&mut self, include_archived: bool) -> &mut Self {
+        self.qualifiers.include_archived = include_archived;
+        self
+    }
+
+    /// Only search for repositories owned by this user or organization.
+    pub fn owner(&mut self, owner: &str) -> &mut Self {
+        self.qualifiers.owner = Some(owner.to_string());
+        self
+    }
+
+    /// Add raw GitHub search qualifiers like `license:mit size:<1000` to the search.
+    ///
+    /// See <https://docs.github.com/en/search-github/searching-on-github/searching-for-repositories>
+    pub fn extra_qualifiers(&mut self, qualifiers: &str) -> &mut Self {
+        self.qualifiers.extra = Some(qualifiers.to_string());
+        self
+    }
+
+    /// Skip repositories where less than `min_percent_rust` percent of the code is written in Rust.
+    ///
+    /// GitHub's `language:rust` qualifier matches any repository that contains some Rust,
+    /// e.g. a C++ project with a small Rust binding.
+    pub fn min_percent_rust(&mut self, min_percent_rust: f64) -> &mut Self {
+        self.min_percent_rust = Some(min_percent_rust);
+        self
+    }
+
     /// Set the max number of pages to fetch from GitHub when iterating over [RepoSearchResults].
     pub fn max_pages(&mut self, max_requests: usize) -> &mut Self {
         self.max_requests = Some(max_requests);
         self
     }
 
+    /// Set whether searches that match more than 1000 repositories should be split into smaller
+    /// searches. GitHub only returns the first 1000 results of any search.
+    pub fn partition_queries(&mut self, partition_queries: bool) -> &mut Self {
+        self.partition_queries = partition_queries;
+        self
+    }
+
+    /// Share a budget of GraphQL points with other requests made during this run.
+    /// Requests stop once the budget is spent.
+    pub fn point_budget(&mut self, budget: &PointBudget) -> &mut Self {
+        self.budget = budget.clone();
+        self
+    }
+
+    /// Set how requests that fail with a timeout or a server error are retried.
+    pub fn retry_policy(&mut self, retry_policy: RetryPolicy) -> &mut Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Continue the search after a cursor returned by [RepoSearchResults::cursor].
+    ///
+    /// The cursor is used to paginate the whole search, so searches resumed this way aren't
+    /// split up and only return up to 1000 results. Use
+    /// [resume_from](GitHubRepoSearch::resume_from) to resume searches that were split up.
+    pub fn after(&mut self, cursor: &str) -> &mut Self {
+        self.resume = Some(Resume::After(cursor.to_string()));
+        self
+    }
+
+    /// Continue the search from a checkpoint taken by [RepoSearchResults::checkpoint].
+    ///
+    /// The checkpoint must come from a search with the same [query](GitHubRepoSearch::query).
+    pub fn resume_from(&mut self, checkpoint: SearchCheckpoint) -> &mut Self {
+        self.resume = Some(Resume::Checkpoint(checkpoint));
+        self
+    }
+
+    /// The GitHub search query for the whole search, before it's split up. Searches with the
+    /// same query find the same repositories, so it identifies the search's checkpoints.
+    pub fn query(&self) -> String {
+        self.root_slices()
+            .iter()
+            .map(|slice| {
+                github_repository_search_string(slice, &self.qualifiers, self.repo_name.as_ref())
+            })
+            .collect::<Vec<_>>()
+            .join(" OR ")
+    }
+
+    /// The slices covering the whole search. There's one slice per topic when searching for
+    /// [any_topics](GitHubRepoSearch::any_topics), and a single slice otherwise.
+    fn root_slices(&self) -> Vec<SearchSlice> {
+        let slice = SearchSlice::new(
+            StarRange {
+                min: self.min_stars,
+                max: self.max_stars,
+            },
+            self.created,
+            self.pushed,
+        );
+        if self.qualifiers.any_topics.is_empty() || self.repo_name.is_some() {
+            return vec![slice];
+        }
+        self.qualifiers
+            .any_topics
+            .iter()
+            .map(|topic| slice.with_topic(topic))
+            .collect()
+    }
+
+    /// Make sure the star range and date windows can match some repositories.
+    fn validate(&self) -> Result<(), SearchError> {
+        if let Some(max_stars) = self.max_stars.filter(|max| *max < self.min_stars) {
+            return Err(SearchError::InvalidQuery(format!(
+                "the max stars ({max_stars}) are below the min stars ({})",
+                self.min_stars
+            )));
+        }
+        for (name, window) in [("created", self.created), ("pushed", self.pushed)] {
+            let Some((start, end)) = window.and_then(|window| window.start.zip(window.end)) else {
+                continue;
+            };
+            if start > end {
+                return Err(SearchError::InvalidQuery(format!(
+                    "the {name} window starts on {start}, after it ends on {end}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
     /// Set the repository name to search for
     pub fn repository_name(&mut self, name: &str) -> &mut Self {
         self.repo_name = Some(Repo::from_str(name).expect("infallible conversion"));
@@ -76,33 +328,59 @@ impl<'a> GitHubRepoSearch<'a> {
     ///     println!(
     ///         "Name: {}\nLatest Commit: {}\nPushed At: {}\n% Written in Rust {:.2}%",
     ///         repository.name_with_owner(),
-    ///         repository.commit_hash(),
+    ///         repository.commit_hash().unwrap_or("none"),
     ///         repository.pushed_at(),
     ///         repository.percent_of_code_in_rust(),
     ///     );
     /// }
     /// ```
-    pub fn search(self) -> Option<RepoSearchResults> {
-        let mut headers = header::HeaderMap::new();
-
-        let bearer_token =
-            header::HeaderValue::from_str(&format!("Bearer {}", self.api_key)).ok()?;
-        headers.insert(header::AUTHORIZATION, bearer_token);
-        let client = reqwest::blocking::ClientBuilder::new()
-            .user_agent(self.user_agent)
-            .default_headers(headers)
-            .build()
-            .ok()?;
-
-        Some(RepoSearchResults {
+    ///
+    /// Returns [SearchError::Auth] if the API key can't be used as a Bearer token, and
+    /// [SearchError::InvalidQuery] if the star range or a date window is empty.
+    pub fn search(self) -> Result<RepoSearchResults, SearchError> {
+        self.validate()?;
+        // Pending slices are searched from the back
+        let mut root_slices = self.root_slices();
+        root_slices.reverse();
+        let uncounted_slices = match self.resume {
+            // We can't tell how much of the search was left when the checkpoint was saved
+            Some(Resume::Checkpoint(_)) => vec![],
+            None | Some(Resume::After(_)) => root_slices.clone(),
+        };
+        let mut client = github_client(self.api_key, self.user_agent).ok_or(SearchError::Auth)?;
+        client.set_point_budget(self.budget);
+        client.set_retry_policy(self.retry_policy);
+
+        let (current_slice, cursor, pending_slices) = match self.resume {
+            None => (None, None, root_slices),
+            Some(Resume::After(cursor)) => (root_slices.pop(), Some(cursor), root_slices),
+            Some(Resume::Checkpoint(checkpoint)) => (
+                checkpoint.current_slice,
+                checkpoint.cursor,
+                checkpoint.pending_slices,
+            ),
+        };
+
+        Ok(RepoSearchResults {
             client,
-            next_page: None,
-            min_stars: self.min_stars,
+            cursor,
             limit: self.limit,
             successful_requests_made: 0,
+            total_repository_count: None,
+            uncounted_slices,
             max_requests: self.max_requests,
             repo_name: self.repo_name,
+            partition_queries: self.partition_queries,
+            current_slice,
+            qualifiers: self.qualifiers,
+            min_percent_rust: self.min_percent_rust,
+            rejected_repositories: 0,
+            pending_slices,
+            seen_repositories: HashSet::new(),
+            graphql_errors: vec![],
+            rejected_nodes: vec![],
             buffered_repos: VecDeque::with_capacity(self.limit),
+            runtime: BlockingRuntime::default(),
         })
     }
 }
@@ -148,20 +426,136 @@ impl<'a> IntoIterator for GitHubRepoSearch<'a> {
 ///
 /// You can instantiate [RepoSearchResults] by using [GitHubRepoSearch::search]
 pub struct RepoSearchResults {
-    client: reqwest::blocking::Client,
-    next_page: Option<String>,
-    min_stars: usize,
+    client: GitHubClient,
+    /// The cursor to continue the current slice from
+    cursor: Option<String>,
     limit: usize,
     successful_requests_made: usize,
     max_requests: Option<usize>,
+    /// The number of repositories that match the search, once we've heard from GitHub
+    total_repository_count: Option<usize>,
+    /// Root slices whose repositories haven't been added to the total yet
+    uncounted_slices: Vec<SearchSlice>,
     repo_name: Option<Repo>,
+    /// Qualifiers applied to every slice of the search
+    qualifiers: SearchQualifiers,
+    /// Repositories with less Rust than this are filtered out
+    min_percent_rust: Option<f64>,
+    /// The number of repositories filtered out because they didn't have enough Rust
+    rejected_repositories: usize,
+    /// Whether searches with more than [MAX_SEARCH_RESULTS] results should be split up
+    partition_queries: bool,
+    /// The slice of the search space that's currently being paginated
+    current_slice: Option<SearchSlice>,
+    /// Slices that still need to be searched
+    pending_slices: Vec<SearchSlice>,
+    /// GraphQL IDs of repositories that were already returned. Repositories can show up in more
+    /// than one slice if their stars change while we're searching.
+    seen_repositories: HashSet<String>,
+    /// Errors from responses that only had partial data
+    graphql_errors: Vec<GraphQLError>,
+    /// Search results that couldn't be deserialized into a repository
+    rejected_nodes: Vec<RejectedNode>,
     buffered_repos: VecDeque<Repository>,
+    /// Drives [next_page](RepoSearchResults::next_page) when iterating
+    runtime: BlockingRuntime,
 }
 
 impl RepoSearchResults {
     /// Returns the token you can use to query the next page of data if there is one.
-    pub fn next_page(&self) -> Option<&str> {
-        self.next_page.as_deref()
+    pub fn cursor(&self) -> Option<&str> {
+        self.cursor.as_deref()
+    }
+
+    /// Where the search left off, so that it can be resumed later with
+    /// [resume_from](GitHubRepoSearch::resume_from). Pages that were already returned won't be
+    /// requested again.
+    pub fn checkpoint(&self) -> SearchCheckpoint {
+        SearchCheckpoint {
+            current_slice: self.current_slice.clone(),
+            cursor: self.cursor.clone(),
+            pending_slices: self.pending_slices.clone(),
+        }
+    }
+
+    /// Is there another page of results to request?
+    ///
+    /// This is `true` until GitHub says the last page of the last slice of the search was
+    /// returned, or until the max number of pages have been requested.
+    pub fn has_next_page(&self) -> bool {
+        let under_max_requests = self
+            .max_requests
+            .is_none_or(|max_requests| self.successful_requests_made < max_requests);
+        under_max_requests && (self.current_slice.is_some() || !self.pending_slices.is_empty())
+    }
+
+    /// Has every part of the search been paginated to the end? Unlike
+    /// [has_next_page](RepoSearchResults::has_next_page) this ignores the `max_pages` limit.
+    pub fn is_finished(&self) -> bool {
+        self.current_slice.is_none() && self.pending_slices.is_empty()
+    }
+
+    /// The number of repositories GitHub says match the search, which can be more than the
+    /// 1000 GitHub returns for a single search. [None] until the first request was made, and
+    /// for searches resumed from a checkpoint.
+    ///
+    /// Searches for [any_topics](GitHubRepoSearch::any_topics) add each topic's repositories
+    /// once its search starts, so the total grows as the search goes on.
+    pub fn total_repository_count(&self) -> Option<usize> {
+        self.total_repository_count
+    }
+
+    /// Errors for the parts of each page that GitHub couldn't resolve. The rest of those pages
+    /// were still returned.
+    pub fn graphql_errors(&self) -> &[GraphQLError] {
+        &self.graphql_errors
+    }
+
+    /// Search results that were skipped because they couldn't be deserialized into a
+    /// repository, along with the reason why.
+    pub fn rejected_nodes(&self) -> &[RejectedNode] {
+        &self.rejected_nodes
+    }
+
+    /// Turn the search into a [Stream] of repositories, requesting each page as the previous one
+    /// is used up.
+    ///
+    /// The stream ends after the first error.
+    ///
+    /// ```no_run
+    /// # use futures::TryStreamExt;
+    /// # use rustfmt_user_config_db::{GitHubRepoSearch, SearchError};
+    /// # async fn search() -> Result<(), SearchError> {
+    /// let mut github_search = GitHubRepoSearch::new(&"MY_API_TOKEN");
+    /// github_search.max_pages(10);
+    /// let repositories = github_search.search()?.into_stream();
+    /// futures::pin_mut!(repositories);
+    /// while let Some(repository) = repositories.try_next().await? {
+    ///     println!("{}", repository.name_with_owner());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn into_stream(self) -> impl Stream<Item = Result<Repository, SearchError>> {
+        futures::stream::unfold(Some(self), |results| async move {
+            let mut results = results?;
+            loop {
+                if let Some(repository) = results.buffered_repos.pop_front() {
+                    return Some((Ok(repository), Some(results)));
+                }
+                match results.next_page().await {
+                    Ok(Some(repositories)) => results.buffered_repos.extend(repositories),
+                    Ok(None) => return None,
+                    Err(err) => return Some((Err(err), None)),
+                }
+            }
+        })
+    }
+
+    /// The number of repositories that were skipped because less than
+    /// [min_percent_rust](GitHubRepoSearch::min_percent_rust) of their code is written in Rust.
+    pub fn rejected_repositories(&self) -> usize {
+        self.rejected_repositories
     }
 
     /// Makes an API call for the next page of data.
@@ -169,81 +563,281 @@ impl RepoSearchResults {
     /// Each page will contain up to *`n`* repositories, where *`n`* is configured using
     /// [repositories_per_page](GitHubRepoSearch::repositories_per_page).
     ///
-    /// [get_next_page](RepoSearchResults::get_next_page) will stop returning results once the
+    /// GitHub only returns the first 1000 results of a search. When a search matches more
+    /// repositories than that, it's split by star count, creation date, and last pushed date
+    /// until each slice has fewer than 1000 results, and each slice is searched in turn.
+    /// Repositories that show up in more than one slice are only returned once.
+    ///
+    /// [next_page](RepoSearchResults::next_page) will stop returning results once the
     /// max_requests pages have been returned. The number of pages one is allowed to request
-    /// can be configured using [max_pages](GitHubRepoSearch::max_pages)
-    pub fn get_next_page(&mut self) -> Option<Vec<Repository>> {
-        if let Some(max_requests) = self.max_requests {
-            if max_requests <= self.successful_requests_made {
-                return None;
-            }
+    /// can be configured using [max_pages](GitHubRepoSearch::max_pages), and return
+    /// `Ok(None)` once they have or once every slice was searched.
+    ///
+    /// A failed request returns an error without losing our place, so calling
+    /// [next_page](RepoSearchResults::next_page) again requests the same page.
+    pub async fn next_page(&mut self) -> Result<Option<Vec<Repository>>, SearchError> {
+        if !self.has_next_page() {
+            return Ok(None);
         }
 
-        let variables = github_repository_search_variables(
-            self.limit,
-            self.next_page(),
-            Some(self.min_stars),
-            self.repo_name.as_ref(),
-        );
+        let slice = match self.current_slice.take() {
+            Some(slice) => slice,
+            None => match self.next_slice().await? {
+                Some(slice) => slice,
+                None => return Ok(None),
+            },
+        };
+        let search_string =
+            github_repository_search_string(&slice, &self.qualifiers, self.repo_name.as_ref());
 
-        let body = serde_json::json!({
-            "operationName": "GitHubRepositorySearch",
-            "query": GITHUB_REPOSITORY_QUERY,
-            "variables": variables
-        });
-
-        let request_body = body.to_string();
-        tracing::trace!(request_body=?request_body);
-
-        let text = self
-            .client
-            .post(GITHUB_GRAPHQL_URL)
-            .body(request_body)
-            .send()
-            .and_then(|resp| resp.text())
-            .map_err(|err| {
-                tracing::error!(request_error=?err);
-                err
-            })
-            .ok()?;
-
-        tracing::trace!(response_body = text);
-        let search_results = GraphQLResponse::<GitHubSearchResult>::new(text)
-            .map_err(|err| {
-                tracing::error!(serialization_error=?err);
-                err
-            })
-            .ok()
-            .and_then(|graphql_response| {
-                if graphql_response.data.is_some() {
-                    graphql_response.data
-                } else {
-                    tracing::error!(graphql_response_err=?graphql_response.error);
-                    None
+        let GraphQLData {
+            data: mut search_results,
+            errors,
+        }: GraphQLData<GitHubSearchResult> = loop {
+            let variables =
+                github_repository_search_variables(self.limit, self.cursor(), &search_string);
+            match post_graphql(
+                &self.client,
+                "GitHubRepositorySearch",
+                GITHUB_REPOSITORY_QUERY,
+                variables,
+            )
+            .await
+            {
+                Ok(search_results) => break search_results,
+                // Large pages of repositories are expensive for GitHub to resolve, so ask for
+                // fewer repositories when the query keeps timing out.
+                Err(err) if err.is_timeout() && self.limit > MIN_PAGE_SIZE => {
+                    self.limit = (self.limit / 2).max(MIN_PAGE_SIZE);
+                    tracing::warn!(
+                        limit = self.limit,
+                        "search timed out, retrying with a smaller page"
+                    );
                 }
-            })?;
+                Err(err) => {
+                    // Keep the slice so the page can be requested again
+                    self.current_slice = Some(slice);
+                    return Err(err);
+                }
+            }
+        };
 
         let total_repository_count = search_results.total_repository_count();
         tracing::debug!(total_repository_count);
+        // Searches that are partitioned were already counted before the first page
+        self.count_root_slice(&slice, total_repository_count);
+        self.graphql_errors.extend(errors);
+        self.rejected_nodes
+            .extend(search_results.take_rejected_nodes());
 
-        if let Some(next_page) = search_results.next_page() {
-            self.next_page = Some(next_page.to_string());
+        // Without a cursor we'd request the first page of the slice all over again
+        match search_results.next_page() {
+            Some(cursor) if search_results.has_next_page() => {
+                self.cursor = Some(cursor.to_string());
+                self.current_slice = Some(slice);
+            }
+            _ => {
+                if search_results.has_next_page() {
+                    tracing::warn!(
+                        search_string,
+                        "no cursor for the next page, ending the slice"
+                    );
+                }
+                // Move on to the next slice
+                self.cursor = None;
+            }
         }
 
         self.successful_requests_made += 1;
-        Some(search_results.into_repositories())
+        let min_percent_rust = self.min_percent_rust.unwrap_or(0.0);
+        Ok(Some(
+            search_results
+                .into_repositories()
+                .into_iter()
+                .filter(|repo| self.seen_repositories.insert(repo.id().to_string()))
+                .filter(|repo| {
+                    let percent_of_code_in_rust = repo.percent_of_code_in_rust();
+                    if percent_of_code_in_rust >= min_percent_rust {
+                        return true;
+                    }
+                    tracing::debug!(
+                        repo = repo.name_with_owner(),
+                        percent_of_code_in_rust,
+                        "not enough rust"
+                    );
+                    self.rejected_repositories += 1;
+                    false
+                })
+                .collect(),
+        ))
+    }
+
+    /// Add the repositories in `slice` to the total if it's one of the root slices and it
+    /// wasn't counted yet. Slices split off of a root slice are already part of its count.
+    fn count_root_slice(&mut self, slice: &SearchSlice, total_repository_count: usize) {
+        let Some(index) = self.uncounted_slices.iter().position(|root| root == slice) else {
+            return;
+        };
+        self.uncounted_slices.swap_remove(index);
+        *self.total_repository_count.get_or_insert(0) += total_repository_count;
+    }
+
+    /// Find the next slice with fewer than [MAX_SEARCH_RESULTS] results,
+    /// splitting larger slices as needed.
+    async fn next_slice(&mut self) -> Result<Option<SearchSlice>, SearchError> {
+        loop {
+            let Some(slice) = self.pending_slices.pop() else {
+                return Ok(None);
+            };
+            if !self.partition_queries || self.repo_name.is_some() {
+                return Ok(Some(slice));
+            }
+
+            let search_string = github_repository_search_string(&slice, &self.qualifiers, None);
+            let count: GitHubSearchCount = match post_graphql(
+                &self.client,
+                "GitHubRepositoryCount",
+                GITHUB_REPOSITORY_COUNT_QUERY,
+                github_repository_count_variables(&search_string),
+            )
+            .await
+            {
+                Ok(count) => count.data,
+                Err(err) => {
+                    // Put the slice back so it's counted again on the next call
+                    self.pending_slices.push(slice);
+                    return Err(err);
+                }
+            };
+
+            let total_repository_count = count.total_repository_count();
+            self.count_root_slice(&slice, total_repository_count);
+            if total_repository_count < MAX_SEARCH_RESULTS {
+                tracing::debug!(search_string, total_repository_count, "searching slice");
+                return Ok(Some(slice));
+            }
+
+            match slice.split() {
+                Some((first, second)) => {
+                    // `pending_slices` is a stack, so push the slice we want to search first last.
+                    self.pending_slices.push(second);
+                    self.pending_slices.push(first);
+                }
+                None => {
+                    tracing::warn!(
+                        search_string,
+                        total_repository_count,
+                        "can't split search any further. Only the first 1000 results will be returned"
+                    );
+                    return Ok(Some(slice));
+                }
+            }
+        }
     }
 }
 
+/// A thin blocking wrapper over [next_page](RepoSearchResults::next_page) for callers that
+/// aren't async. Iteration stops at the first error, which is logged. Must not be used from
+/// within an async runtime, use [into_stream](RepoSearchResults::into_stream) instead.
 impl Iterator for RepoSearchResults {
     type Item = Repository;
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(repo_info) = self.buffered_repos.pop_front() {
-            return Some(repo_info);
+        // Pages can be empty when all of their repositories were already returned
+        while self.buffered_repos.is_empty() {
+            let mut runtime = std::mem::take(&mut self.runtime);
+            let page = runtime.block_on(self.next_page());
+            self.runtime = runtime;
+            match page {
+                Ok(repositories) => self.buffered_repos.extend(repositories?),
+                Err(err) => {
+                    tracing::error!(search_error = %err, "stopping the search");
+                    return None;
+                }
+            }
         }
-
-        let repositories = self.get_next_page()?;
-        self.buffered_repos.extend(repositories);
         self.buffered_repos.pop_front()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_query() {
+        let search = GitHubRepoSearch::new("token");
+        assert_eq!(
+            search.query(),
+            "topic:rust stars:>=50 language:rust template:false archived:false"
+        );
+    }
+
+    #[test]
+    fn query_without_topics() {
+        let mut search = GitHubRepoSearch::new("token");
+        search.topics(Vec::<String>::new());
+        assert_eq!(
+            search.query(),
+            "stars:>=50 language:rust template:false archived:false"
+        );
+    }
+
+    #[test]
+    fn query_with_every_topic() {
+        let mut search = GitHubRepoSearch::new("token");
+        search.topics(["rust", "cli"]);
+        assert_eq!(
+            search.query(),
+            "topic:rust topic:cli stars:>=50 language:rust template:false archived:false"
+        );
+    }
+
+    #[test]
+    fn query_with_any_topic() {
+        let mut search = GitHubRepoSearch::new("token");
+        search
+            .topics(Vec::<String>::new())
+            .any_topics(["cli", "wasm"]);
+        assert_eq!(
+            search.query(),
+            "stars:>=50 topic:cli language:rust template:false archived:false \
+             OR stars:>=50 topic:wasm language:rust template:false archived:false"
+        );
+        assert_eq!(search.root_slices().len(), 2);
+    }
+
+    #[test]
+    fn query_with_open_date_windows_is_the_same_every_day() {
+        let start = Date::from_calendar_date(2020, time::Month::January, 1).unwrap();
+        let mut search = GitHubRepoSearch::new("token");
+        search
+            .created_between(Some(start), None)
+            .pushed_between(None, None);
+        // The query is the checkpoint key, so it can't depend on today's date
+        assert_eq!(
+            search.query(),
+            "topic:rust stars:>=50 created:>=2020-01-01 language:rust template:false archived:false"
+        );
+    }
+
+    #[test]
+    fn sums_the_count_of_each_root_slice() {
+        let mut search = GitHubRepoSearch::new("token");
+        search
+            .topics(Vec::<String>::new())
+            .any_topics(["cli", "wasm"]);
+        let roots = search.root_slices();
+        let mut results = search.search().unwrap();
+        assert_eq!(results.total_repository_count(), None);
+
+        results.count_root_slice(&roots[0], 1500);
+        // Slices split off of a root slice were already counted with it
+        let (low, _) = roots[0].split().unwrap();
+        results.count_root_slice(&low, 700);
+        results.count_root_slice(&roots[0], 1500);
+        assert_eq!(results.total_repository_count(), Some(1500));
+
+        results.count_root_slice(&roots[1], 300);
+        assert_eq!(results.total_repository_count(), Some(1800));
+    }
+}
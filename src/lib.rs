@@ -1,6 +1,33 @@
 pub mod cli;
+mod config_files;
 mod database;
 mod github;
+mod rustfmt_config;
 
-pub use database::store::store_in_db;
-pub use github::{GitHubRepoSearch, ProgrammingLanguage, RepoSearchResults, Repository};
+pub use config_files::{ConfigFile, ConfigFileKind};
+pub use database::checkpoints::{delete_checkpoint, load_checkpoint, save_checkpoint};
+pub use database::crawl_runs::{
+    finish_crawl_run, set_crawl_run_query, start_crawl_run, CrawlParameters, CrawlStats,
+};
+pub use database::denylist::{
+    denylist_repositories, denylisted_repositories, remove_from_denylist, Denylist, DenylistEntry,
+    DenylistRecord,
+};
+pub use database::pinned::{
+    pin_repositories, pinned_repositories, unpin_repositories, PinnedRepository,
+};
+pub use database::repositories::{
+    changed_repositories, mark_repositories_gone, repositories_missing_configs,
+    stored_repository_ids,
+};
+pub use database::store::{store_in_db, store_rustfmt_configs};
+pub use github::{
+    CodeSearchResults, FetchedConfigs, GitHubCodeSearch, GitHubOwnerRepos, GitHubRepoLookup,
+    GitHubRepoSearch, GraphQLError, GraphQLPathSegment, OwnerRepoResults, PointBudget,
+    ProgrammingLanguage, RejectedNode, RepoLookupResults, RepoSearchResults, Repository,
+    RetryPolicy, RustfmtConfigFetcher, RustfmtConfigFile, SearchCheckpoint, SearchError,
+};
+pub use rustfmt_config::{
+    cluster_configs, config_diff, find_similar, ConfigCluster, ConfigDiff, ConfigSource,
+    OptionDiff, RustfmtConfig, SimilarConfig, SimilarityReport, StoredConfig,
+};
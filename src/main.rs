@@ -1,11 +1,34 @@
 use anyhow::Context;
 use clap::Parser;
-use rustfmt_user_config_db::cli::{Cli, Commands};
-use rustfmt_user_config_db::{store_in_db, GitHubRepoSearch, Repository};
-use sqlx::postgres::PgPoolOptions;
+use rustfmt_user_config_db::cli::{Cli, Commands, DateWindow, DenylistCommands, PinCommands};
+use rustfmt_user_config_db::{
+    cluster_configs, config_diff, delete_checkpoint, denylist_repositories,
+    denylisted_repositories, find_similar, finish_crawl_run, load_checkpoint,
+    mark_repositories_gone, pin_repositories, pinned_repositories, remove_from_denylist,
+    repositories_missing_configs, save_checkpoint, set_crawl_run_query, start_crawl_run,
+    store_in_db, store_rustfmt_configs, stored_repository_ids, unpin_repositories, CrawlParameters,
+    CrawlStats, Denylist, FetchedConfigs, GitHubCodeSearch, GitHubOwnerRepos, GitHubRepoLookup,
+    GitHubRepoSearch, PointBudget, RejectedNode, Repository, RetryPolicy, RustfmtConfig,
+    RustfmtConfigFetcher, SearchCheckpoint, SearchError, StoredConfig,
+};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::PgPool;
+use std::path::Path;
+use std::sync::Mutex;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 
+/// The number of repositories to refresh before storing them. GitHub lets us look up at most
+/// 100 repositories with a single `nodes(ids:)` query.
+const REFRESH_BATCH_SIZE: usize = 100;
+
+/// The number of repositories from a `--from-file` list to store at once, so that a long list
+/// doesn't run into the limit on bind parameters in a single query.
+const FROM_FILE_BATCH_SIZE: usize = 100;
+
+/// The most connections to the database that are open at once.
+const MAX_DB_CONNECTIONS: u32 = 20;
+
 fn main() -> anyhow::Result<()> {
     dotenv::dotenv().ok();
 
@@ -14,66 +37,851 @@ fn main() -> anyhow::Result<()> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let databse_url =
-        std::env::var("DATABASE_URL").context("Must set DATABASE_URL environment variable")?;
-
     let cli = Cli::parse();
-    match cli.command {
+
+    let db_options = match std::env::var("DATABASE_URL") {
+        Ok(database_url) => database_url
+            .parse::<PgConnectOptions>()
+            .context("DATABASE_URL is not a valid Postgres connection string")?,
+        // Diffing local config files doesn't touch the database
+        Err(_) if !cli.command.needs_database() => PgConnectOptions::new(),
+        Err(err) => return Err(err).context("Must set DATABASE_URL environment variable"),
+    };
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(run(cli.command, db_options))
+}
+
+async fn run(command: Commands, db_options: PgConnectOptions) -> anyhow::Result<()> {
+    // Connections are only opened once they're needed, so commands that don't touch the
+    // database never connect to it
+    let db = &PgPoolOptions::new()
+        .max_connections(MAX_DB_CONNECTIONS)
+        .connect_lazy_with(db_options);
+    match command {
         Commands::AddRepositories {
             limit,
             max_pages,
             stars,
+            max_stars,
+            topics,
+            any_topics,
+            no_topic,
+            created,
+            pushed,
+            include_forks,
+            include_templates,
+            include_archived,
+            user,
+            qualifiers,
+            min_percent_rust,
             dry_run,
             repo,
-            ..
+            skip_configs,
+            code_search,
+            from_file,
+            owner,
+            no_partition,
+            max_points,
+            max_retries,
+            resume,
+            after,
+        } => {
+            if let Some(max_stars) = max_stars {
+                anyhow::ensure!(
+                    max_stars >= stars,
+                    "--max-stars ({max_stars}) must be at least --stars ({stars})"
+                );
+            }
+            let github_api_token = std::env::var("GITHUB_API_TOKEN")
+                .context("Must set GITHUB_API_TOKEN environment variable")?;
+
+            let budget = PointBudget::new(max_points);
+            let retry_policy = RetryPolicy::new(max_retries);
+            let config_fetcher = if skip_configs {
+                None
+            } else {
+                let mut fetcher = RustfmtConfigFetcher::new(&github_api_token)
+                    .context("GITHUB_API_TOKEN is not a valid Bearer token")?;
+                fetcher.point_budget(&budget).retry_policy(retry_policy);
+                Some(fetcher)
+            };
+            let mut lookup = GitHubRepoLookup::new(&github_api_token)
+                .context("GITHUB_API_TOKEN is not a valid Bearer token")?;
+            lookup.point_budget(&budget).retry_policy(retry_policy);
+            // Dry runs don't touch the database
+            let denylist = if dry_run {
+                Denylist::default()
+            } else {
+                Denylist::load(db).await?
+            };
+            // Owner listings go through every page unless they're limited, and lists from a
+            // file aren't paged at all
+            let max_pages = if owner.is_some() {
+                max_pages
+            } else if from_file.is_some() {
+                None
+            } else {
+                Some(max_pages.unwrap_or(1))
+            };
+            // Only searches filter by stars
+            let is_search = !code_search && from_file.is_none() && owner.is_none();
+            let min_stars = is_search.then_some(stars);
+            let run_id = if dry_run {
+                None
+            } else {
+                let run_id =
+                    start_run(db, "add-repo", min_stars, max_pages.map(Into::into)).await?;
+                Some(run_id)
+            };
+            let ingestion = Ingestion {
+                db,
+                config_fetcher,
+                denylist,
+                dry_run,
+                run_id,
+                log: Mutex::default(),
+            };
+
+            let mut crawl = if dry_run {
+                Ok(())
+            } else {
+                ingestion.refresh_pinned(&lookup).await
+            };
+            if crawl.is_ok() {
+                crawl = if code_search {
+                    let mut code_search = GitHubCodeSearch::new(&github_api_token);
+                    code_search
+                        .max_pages(max_pages.unwrap_or(1).into())
+                        .point_budget(&budget)
+                        .retry_policy(retry_policy);
+                    crawl_code_search(&ingestion, code_search).await
+                } else if let Some(path) = from_file {
+                    crawl_from_file(&ingestion, &lookup, &path).await
+                } else if let Some(owner) = owner {
+                    let mut owner_repos = GitHubOwnerRepos::new(&github_api_token, &owner);
+                    owner_repos
+                        .repositories_per_page(limit as usize)
+                        .include_forks(include_forks)
+                        .point_budget(&budget)
+                        .retry_policy(retry_policy);
+                    if let Some(max_pages) = max_pages {
+                        owner_repos.max_pages(max_pages.into());
+                    }
+                    if let Some(min_percent_rust) = min_percent_rust {
+                        owner_repos.min_percent_rust(min_percent_rust);
+                    }
+                    crawl_owner(&ingestion, owner_repos, &owner).await
+                } else {
+                    let mut github_search = GitHubRepoSearch::new(&github_api_token);
+                    github_search
+                        .repositories_per_page(limit as usize)
+                        .max_pages(max_pages.unwrap_or(1).into())
+                        .min_stars(stars as usize)
+                        .include_forks(include_forks)
+                        .include_templates(include_templates)
+                        .include_archived(include_archived)
+                        .partition_queries(!no_partition)
+                        .point_budget(&budget)
+                        .retry_policy(retry_policy);
+
+                    if let Some(max_stars) = max_stars {
+                        github_search.max_stars(max_stars as usize);
+                    }
+                    if no_topic || (topics.is_empty() && !any_topics.is_empty()) {
+                        github_search.topics(Vec::<String>::new());
+                    } else if !topics.is_empty() {
+                        github_search.topics(topics);
+                    }
+                    // The cursor only covers the whole search when it isn't split up
+                    let print_cursor = no_partition && any_topics.is_empty();
+                    if !any_topics.is_empty() {
+                        github_search.any_topics(any_topics);
+                    }
+                    if let Some(DateWindow { start, end }) = created {
+                        github_search.created_between(start, end);
+                    }
+                    if let Some(DateWindow { start, end }) = pushed {
+                        github_search.pushed_between(start, end);
+                    }
+                    if let Some(user) = user {
+                        github_search.owner(&user);
+                    }
+                    if let Some(qualifiers) = qualifiers {
+                        github_search.extra_qualifiers(&qualifiers);
+                    }
+                    if let Some(min_percent_rust) = min_percent_rust {
+                        github_search.min_percent_rust(min_percent_rust);
+                    }
+
+                    if let Some(name) = repo {
+                        github_search.repository_name(&name);
+                    }
+
+                    crawl_search(
+                        &ingestion,
+                        github_search,
+                        resume,
+                        after,
+                        print_cursor,
+                        min_percent_rust,
+                    )
+                    .await
+                };
+            }
+
+            if let Some(max_points) = max_points {
+                println!(
+                    "Spent {} of {max_points} GraphQL points",
+                    budget.points_spent()
+                );
+            }
+            let crawl = ingestion.stop_on_spent_budget(crawl);
+            ingestion.finish(&crawl).await?;
+            crawl?;
+        }
+        Commands::Refresh {
+            limit,
+            include_gone,
+            skip_configs,
+            max_points,
+            max_retries,
         } => {
             let github_api_token = std::env::var("GITHUB_API_TOKEN")
                 .context("Must set GITHUB_API_TOKEN environment variable")?;
+            let budget = PointBudget::new(max_points);
+            let retry_policy = RetryPolicy::new(max_retries);
+            let mut lookup = GitHubRepoLookup::new(&github_api_token)
+                .context("GITHUB_API_TOKEN is not a valid Bearer token")?;
+            lookup.point_budget(&budget).retry_policy(retry_policy);
+            let config_fetcher = if skip_configs {
+                None
+            } else {
+                let mut fetcher = RustfmtConfigFetcher::new(&github_api_token)
+                    .context("GITHUB_API_TOKEN is not a valid Bearer token")?;
+                fetcher.point_budget(&budget).retry_policy(retry_policy);
+                Some(fetcher)
+            };
 
-            let mut github_search = GitHubRepoSearch::new(&github_api_token);
-            github_search
-                .repositories_per_page(limit as usize)
-                .max_pages(max_pages as usize)
-                .min_stars(stars as usize);
+            let ids = stored_repository_ids(db, limit.map(i64::from), include_gone).await?;
+            let denylist = Denylist::load(db).await?;
+            let run_id = start_run(db, "refresh", None, None).await?;
+            let ingestion = Ingestion {
+                db,
+                config_fetcher,
+                denylist,
+                dry_run: false,
+                run_id: Some(run_id),
+                log: Mutex::default(),
+            };
 
-            if let Some(name) = repo {
-                github_search.repository_name(&name);
+            let crawl = refresh_repositories(&ingestion, &lookup, &ids).await;
+            if let Some(max_points) = max_points {
+                println!(
+                    "Spent {} of {max_points} GraphQL points",
+                    budget.points_spent()
+                );
             }
+            let crawl = ingestion.stop_on_spent_budget(crawl);
+            ingestion.finish(&crawl).await?;
+            crawl?;
+        }
+        Commands::Pin { command } => {
+            run_pin_command(db, command).await?;
+        }
+        Commands::Denylist { command } => {
+            run_denylist_command(db, command).await?;
+        }
+        Commands::ConfigDiff { left, right } => {
+            let db = (left.needs_database() || right.needs_database()).then_some(db);
+            let left = left.load(db).await?;
+            let right = right.load(db).await?;
+            let diff = config_diff(&left, &right);
 
-            let mut search_results = github_search.search().unwrap();
+            if diff.is_empty() {
+                println!("The configs are equivalent");
+            } else {
+                print!("{diff}");
+            }
+        }
+        Commands::Similar { path, limit } => {
+            let config = RustfmtConfig::from_file(&path)?;
+            let corpus = StoredConfig::load_all(db).await?;
 
-            // FIXME(ytmim) Need to create the async runtime manually because it doesn't play well with the
-            // syncronous `RepoSearchResults`. To get around this it probably makes sense to drop the
-            // Iterator impl for `RepoSearchResults` and instead make `get_next_page` async.
-            let runtime = tokio::runtime::Builder::new_multi_thread()
-                .enable_all()
-                .build()?;
+            let report = find_similar(&config, &corpus, limit);
+            for similar in &report.nearest {
+                println!(
+                    "{:>6.2}% {} ({})",
+                    similar.similarity * 100.0,
+                    similar.config.repo_name,
+                    similar.config.file_path
+                );
+            }
+            println!(
+                "More unusual than {:.1}% of {} stored configs",
+                report.unusualness_percentile,
+                corpus.len()
+            );
+        }
+        Commands::Presets {
+            min_similarity,
+            min_size,
+            representatives,
+        } => {
+            let corpus = StoredConfig::load_all(db).await?;
+            let clusters = cluster_configs(&corpus, min_similarity);
 
-            while let Some(repositories) = search_results.get_next_page() {
-                if dry_run {
-                    for repo in repositories {
-                        println!("{repo:#}")
-                    }
-                    continue;
+            for cluster in clusters.iter().filter(|c| c.size() >= min_size) {
+                println!(
+                    "{} configs ({:.1}%): {cluster}",
+                    cluster.size(),
+                    cluster.size() as f64 / corpus.len() as f64 * 100.0
+                );
+                for stored in cluster.representatives(representatives) {
+                    println!("    {} ({})", stored.repo_name, stored.file_path);
                 }
-                runtime.block_on(run_store_in_db(&databse_url, repositories))?;
             }
-            println!("Next Token: {:?}", search_results.next_page());
         }
     }
 
     Ok(())
 }
 
-async fn run_store_in_db(
-    connection_str: &str,
-    repositories: Vec<Repository>,
+/// Stores each page of repositories found while searching GitHub.
+struct Ingestion<'a> {
+    db: &'a PgPool,
+    /// Used to fetch the rustfmt configs of each repository unless `--skip-configs` was set.
+    config_fetcher: Option<RustfmtConfigFetcher>,
+    /// Repositories that must be skipped
+    denylist: Denylist,
+    /// Print the repositories instead of storing them
+    dry_run: bool,
+    /// The crawl that stored repositories are linked to
+    run_id: Option<i64>,
+    /// What's recorded in `crawl_runs` once the crawl is done
+    log: Mutex<CrawlLog>,
+}
+
+/// What happened during a crawl.
+#[derive(Default)]
+struct CrawlLog {
+    stats: CrawlStats,
+    errors: Vec<String>,
+}
+
+impl Ingestion<'_> {
+    async fn ingest(&self, repositories: Vec<Repository>) -> anyhow::Result<()> {
+        let fetched = repositories.len();
+        let repositories = self.denylist.filter(repositories);
+        if self.dry_run {
+            for repo in repositories {
+                println!("{repo:#}")
+            }
+            return Ok(());
+        }
+
+        let denylisted = fetched - repositories.len();
+        // Skip fetching configs that were already fetched at the repository's latest commit
+        let missing_configs = repositories_missing_configs(self.db, &repositories).await?;
+        let (missing_configs, up_to_date): (Vec<_>, Vec<_>) = repositories
+            .into_iter()
+            .partition(|repo| missing_configs.contains(repo.id()));
+        // The repositories are still worth storing when their configs can't be fetched. They keep
+        // their old `configs_fetched_commit`, so their configs are fetched again next time.
+        let (configs, budget_exhausted) = match &self.config_fetcher {
+            Some(config_fetcher) => match config_fetcher.fetch(&missing_configs).await {
+                Ok(configs) => (Some(configs), None),
+                Err(err @ SearchError::BudgetExhausted { .. }) => (None, Some(err)),
+                Err(err) => {
+                    println!("Could not fetch rustfmt configs: {err}");
+                    self.record_error(format!("Could not fetch rustfmt configs: {err}"));
+                    (None, None)
+                }
+            },
+            None => (None, None),
+        };
+        let repositories = missing_configs.into_iter().chain(up_to_date).collect();
+        let stats = run_store_in_db(self.db, repositories, configs, self.run_id).await?;
+        self.record(CrawlStats {
+            fetched: stats.fetched + denylisted,
+            rejected: stats.rejected + denylisted,
+            ..stats
+        });
+        match budget_exhausted {
+            Some(err) => Err(err).context("Failed to fetch rustfmt configs"),
+            None => Ok(()),
+        }
+    }
+
+    /// Stopping early is what the point budget is for, so a crawl that stopped because the
+    /// budget was spent still succeeded with whatever it stored before then.
+    fn stop_on_spent_budget(&self, crawl: anyhow::Result<()>) -> anyhow::Result<()> {
+        match crawl {
+            Err(err) if is_budget_exhausted(&err) => {
+                println!("Stopping the crawl: {err:#}");
+                self.record_error(format!("{err:#}"));
+                Ok(())
+            }
+            crawl => crawl,
+        }
+    }
+
+    /// Add to the stats recorded for the crawl.
+    fn record(&self, stats: CrawlStats) {
+        self.log
+            .lock()
+            .expect("crawl log lock isn't poisoned")
+            .stats += stats;
+    }
+
+    /// Count repositories that GitHub returned but were skipped before they were stored.
+    fn reject(&self, rejected: usize) {
+        self.record(CrawlStats {
+            fetched: rejected,
+            rejected,
+            ..CrawlStats::default()
+        });
+    }
+
+    /// Remember an error to record for the crawl.
+    fn record_error(&self, error: impl std::fmt::Display) {
+        self.log
+            .lock()
+            .expect("crawl log lock isn't poisoned")
+            .errors
+            .push(error.to_string());
+    }
+
+    /// Print and record the entries or IDs that couldn't be resolved to a repository.
+    fn report_unresolved(&self, unresolved: &[String]) {
+        for entry in unresolved {
+            println!("Could not resolve: {entry}");
+            self.record_error(format!("Could not resolve: {entry}"));
+        }
+    }
+
+    /// Print and record the repositories GitHub returned that couldn't be deserialized.
+    fn report_rejected(&self, rejected: &[RejectedNode]) {
+        for node in rejected {
+            let skipped = format!(
+                "Skipped {}: {}",
+                node.name_with_owner
+                    .as_deref()
+                    .or(node.id.as_deref())
+                    .unwrap_or("a result"),
+                node.reason
+            );
+            println!("{skipped}");
+            self.record_error(skipped);
+        }
+    }
+
+    /// Record the GitHub search query the crawl used.
+    async fn record_query(&self, query: &str) -> anyhow::Result<()> {
+        let Some(run_id) = self.run_id else {
+            return Ok(());
+        };
+
+        set_crawl_run_query(self.db, run_id, query).await
+    }
+
+    /// Record the end of the crawl along with the error that stopped it, if any.
+    async fn finish(&self, result: &anyhow::Result<()>) -> anyhow::Result<()> {
+        let Some(run_id) = self.run_id else {
+            return Ok(());
+        };
+
+        if let Err(err) = result {
+            self.record_error(format!("{err:#}"));
+        }
+        let log = std::mem::take(&mut *self.log.lock().expect("crawl log lock isn't poisoned"));
+        finish_crawl_run(self.db, run_id, &log.stats, &log.errors).await
+    }
+
+    /// Remember where the search for `query` left off once a page has been stored.
+    async fn save_checkpoint(
+        &self,
+        query: &str,
+        checkpoint: &SearchCheckpoint,
+    ) -> anyhow::Result<()> {
+        if self.dry_run {
+            return Ok(());
+        }
+
+        save_checkpoint(self.db, query, checkpoint).await
+    }
+
+    /// Forget the checkpoint for `query` once the search has finished.
+    async fn delete_checkpoint(&self, query: &str) -> anyhow::Result<()> {
+        if self.dry_run {
+            return Ok(());
+        }
+
+        delete_checkpoint(self.db, query).await
+    }
+
+    /// Refresh every pinned repository, no matter which search filters the crawl uses.
+    async fn refresh_pinned(&self, lookup: &GitHubRepoLookup) -> anyhow::Result<()> {
+        let pinned = pinned_repositories(self.db).await?;
+        if pinned.is_empty() {
+            return Ok(());
+        }
+
+        let ids = pinned
+            .into_iter()
+            .map(|pinned| pinned.github_graphql_id)
+            .collect::<Vec<_>>();
+        let results = lookup
+            .lookup_ids(&ids)
+            .await
+            .context("Failed to refresh the pinned repositories")?;
+        self.ingest(results.repositories).await?;
+        self.report_unresolved(&results.unresolved);
+        self.report_rejected(&results.rejected);
+        self.mark_gone(&results.not_found).await
+    }
+
+    /// Mark repositories that GitHub says don't exist anymore as gone.
+    async fn mark_gone(&self, github_graphql_ids: &[String]) -> anyhow::Result<()> {
+        if github_graphql_ids.is_empty() {
+            return Ok(());
+        }
+
+        let gone = mark_repositories_gone(self.db, github_graphql_ids).await?;
+        for repo in gone {
+            println!("{repo} was deleted or made private");
+        }
+        Ok(())
+    }
+}
+
+/// Store the repositories that have a rustfmt config, according to GitHub's code search.
+async fn crawl_code_search(
+    ingestion: &Ingestion<'_>,
+    code_search: GitHubCodeSearch<'_>,
+) -> anyhow::Result<()> {
+    let mut search_results = code_search
+        .search()
+        .context("GITHUB_API_TOKEN is not a valid Bearer token")?;
+    while let Some(results) = search_results
+        .get_next_page()
+        .await
+        .context("Failed to search GitHub code")?
+    {
+        ingestion.ingest(results.repositories).await?;
+        // The code search index can lag behind repositories being deleted or made private
+        ingestion.report_unresolved(&results.not_found);
+        ingestion.report_unresolved(&results.unresolved);
+        ingestion.report_rejected(&results.rejected);
+    }
+    Ok(())
+}
+
+/// Store the repositories listed in a file, one `owner/name` or URL per line.
+async fn crawl_from_file(
+    ingestion: &Ingestion<'_>,
+    lookup: &GitHubRepoLookup,
+    path: &Path,
 ) -> anyhow::Result<()> {
-    let db = PgPoolOptions::new()
-        .max_connections(20)
-        .connect(connection_str)
+    let list =
+        std::fs::read_to_string(path).with_context(|| format!("can't read {}", path.display()))?;
+    let entries = list
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+    let results = lookup
+        .lookup(entries)
         .await
-        .context("can't connect to database")?;
+        .context("Failed to look up the repositories")?;
+    let mut repositories = results.repositories.into_iter().peekable();
+    while repositories.peek().is_some() {
+        let batch = repositories.by_ref().take(FROM_FILE_BATCH_SIZE).collect();
+        ingestion.ingest(batch).await?;
+    }
+
+    ingestion.report_unresolved(&results.unresolved);
+    ingestion.report_rejected(&results.rejected);
+    Ok(())
+}
+
+/// Store the Rust repositories of a user or organization.
+async fn crawl_owner(
+    ingestion: &Ingestion<'_>,
+    owner_repos: GitHubOwnerRepos<'_>,
+    owner: &str,
+) -> anyhow::Result<()> {
+    let mut owner_results = owner_repos
+        .list()
+        .context("GITHUB_API_TOKEN is not a valid Bearer token")?;
+    while let Some(repositories) = owner_results
+        .get_next_page()
+        .await
+        .with_context(|| format!("Failed to list the repositories of {owner}"))?
+    {
+        ingestion.ingest(repositories).await?;
+    }
+    println!(
+        "Rejected {} repositories without enough Rust",
+        owner_results.rejected_repositories()
+    );
+    ingestion.reject(owner_results.rejected_repositories());
+    for error in owner_results.graphql_errors() {
+        println!("Skipped part of a page: {error}");
+        ingestion.record_error(error);
+    }
+    ingestion.report_rejected(owner_results.rejected_nodes());
+    Ok(())
+}
+
+/// Store the repositories found by searching GitHub, saving a checkpoint after every page so
+/// that the search can be resumed.
+async fn crawl_search(
+    ingestion: &Ingestion<'_>,
+    mut github_search: GitHubRepoSearch<'_>,
+    resume: bool,
+    after: Option<String>,
+    print_cursor: bool,
+    min_percent_rust: Option<f64>,
+) -> anyhow::Result<()> {
+    let query = github_search.query();
+    ingestion.record_query(&query).await?;
+    if let Some(after) = after {
+        github_search.after(&after);
+    } else if resume {
+        match load_checkpoint(ingestion.db, &query).await? {
+            Some(checkpoint) => {
+                github_search.resume_from(checkpoint);
+            }
+            None => {
+                println!("No checkpoint to resume for `{query}`, starting over")
+            }
+        }
+    }
+
+    let mut search_results = github_search
+        .search()
+        .context("Failed to start the GitHub search")?;
+    // Store each page while the next one is being fetched
+    let mut found = 0;
+    let mut page = search_results.next_page().await;
+    loop {
+        match page {
+            Ok(Some(repositories)) => {
+                found += repositories.len();
+                if let Some(total) = search_results.total_repository_count() {
+                    tracing::info!(found, total, "searching GitHub");
+                }
+                // Taken before fetching the next page so that resuming never
+                // skips a page that wasn't stored
+                let checkpoint = search_results.checkpoint();
+                let (next_page, stored) =
+                    futures::join!(search_results.next_page(), ingestion.ingest(repositories));
+                stored?;
+                ingestion.save_checkpoint(&query, &checkpoint).await?;
+                page = next_page;
+            }
+            Ok(None) => {
+                if search_results.is_finished() {
+                    ingestion.delete_checkpoint(&query).await?;
+                }
+                break;
+            }
+            // Stopping early is what the budget is for, so keep what we found
+            Err(err @ SearchError::BudgetExhausted { .. }) => {
+                println!("Stopping the search: {err}");
+                ingestion.record_error(err);
+                break;
+            }
+            Err(err) => {
+                if print_cursor {
+                    println!("Next Token: {:?}", search_results.cursor());
+                }
+                return Err(err).context("Failed to search GitHub");
+            }
+        }
+    }
+    if print_cursor {
+        println!("Next Token: {:?}", search_results.cursor());
+    }
+    if let Some(total) = search_results.total_repository_count() {
+        println!("Found {found} of {total} matching repositories");
+    }
+    if let Some(min_percent_rust) = min_percent_rust {
+        println!(
+            "Rejected {} repositories with less than {min_percent_rust}% Rust",
+            search_results.rejected_repositories()
+        );
+    }
+    ingestion.reject(search_results.rejected_repositories());
+    for error in search_results.graphql_errors() {
+        println!("Skipped part of a page: {error}");
+        ingestion.record_error(error);
+    }
+    ingestion.report_rejected(search_results.rejected_nodes());
+    Ok(())
+}
+
+/// Look up the stored repositories again, and mark the ones that are gone.
+async fn refresh_repositories(
+    ingestion: &Ingestion<'_>,
+    lookup: &GitHubRepoLookup,
+    ids: &[String],
+) -> anyhow::Result<()> {
+    let mut refreshed = 0;
+    for batch in ids.chunks(REFRESH_BATCH_SIZE) {
+        let results = lookup
+            .lookup_ids(batch)
+            .await
+            .context("Failed to refresh repositories")?;
+        refreshed += results.repositories.len();
+        ingestion.ingest(results.repositories).await?;
+        ingestion.report_unresolved(&results.unresolved);
+        ingestion.report_rejected(&results.rejected);
+        ingestion.mark_gone(&results.not_found).await?;
+    }
+    println!("Refreshed {refreshed} of {} repositories", ids.len());
+    Ok(())
+}
+
+async fn run_pin_command(db: &PgPool, command: PinCommands) -> anyhow::Result<()> {
+    match command {
+        PinCommands::Add { repos, reason } => {
+            let github_api_token = std::env::var("GITHUB_API_TOKEN")
+                .context("Must set GITHUB_API_TOKEN environment variable")?;
+            let lookup = GitHubRepoLookup::new(&github_api_token)
+                .context("GITHUB_API_TOKEN is not a valid Bearer token")?;
+            let config_fetcher = RustfmtConfigFetcher::new(&github_api_token)
+                .context("GITHUB_API_TOKEN is not a valid Bearer token")?;
+
+            let results = lookup
+                .lookup(repos.iter().map(String::as_str))
+                .await
+                .context("Failed to look up the repositories")?;
+            for entry in &results.unresolved {
+                println!("Could not resolve: {entry}");
+            }
+
+            let denylist = Denylist::load(db).await?;
+            let repositories = denylist.filter(results.repositories);
+            if repositories.is_empty() {
+                return Ok(());
+            }
+
+            pin_repositories(db, &repositories, reason.as_deref()).await?;
+            for repo in &repositories {
+                println!("Pinned {}", repo.name_with_owner());
+            }
+
+            let ingestion = Ingestion {
+                db,
+                config_fetcher: Some(config_fetcher),
+                denylist,
+                dry_run: false,
+                run_id: None,
+                log: Mutex::default(),
+            };
+            ingestion.ingest(repositories).await?;
+        }
+        PinCommands::Remove { repos } => {
+            let unpinned = unpin_repositories(db, &repos).await?;
+            for repo in &unpinned {
+                println!("Unpinned {repo}");
+            }
+            if unpinned.len() < repos.len() {
+                println!(
+                    "{} repositories were not pinned",
+                    repos.len() - unpinned.len()
+                );
+            }
+        }
+        PinCommands::List => {
+            let pinned = pinned_repositories(db).await?;
+            for repo in pinned {
+                match repo.reason {
+                    Some(reason) => println!("{} ({reason})", repo.repo_name),
+                    None => println!("{}", repo.repo_name),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Store the repositories, and the rustfmt configs of the repositories that were looked up.
+async fn run_store_in_db(
+    db: &PgPool,
+    repositories: Vec<Repository>,
+    configs: Option<FetchedConfigs>,
+    run_id: Option<i64>,
+) -> anyhow::Result<CrawlStats> {
+    let stats = store_in_db(db.clone(), repositories.into_iter(), run_id).await?;
+
+    if let Some(FetchedConfigs { configs, resolved }) = configs {
+        store_rustfmt_configs(db.clone(), &resolved, configs).await?;
+    }
+    Ok(stats)
+}
+
+/// Record the start of an `add-repo` or `refresh` crawl.
+async fn start_run(
+    db: &PgPool,
+    command: &str,
+    min_stars: Option<u32>,
+    max_pages: Option<u32>,
+) -> anyhow::Result<i64> {
+    let parameters = CrawlParameters {
+        command: command.to_string(),
+        arguments: std::env::args().collect(),
+        min_stars,
+        max_pages,
+    };
+    start_crawl_run(db, &parameters).await
+}
+
+async fn run_denylist_command(db: &PgPool, command: DenylistCommands) -> anyhow::Result<()> {
+    match command {
+        DenylistCommands::Add { entries, reason } => {
+            let purged = denylist_repositories(db, &entries, reason.as_deref()).await?;
+            for entry in &entries {
+                println!("Denylisted {entry}");
+            }
+            for repo in &purged {
+                println!("Purged {repo}");
+            }
+        }
+        DenylistCommands::Remove { entries } => {
+            let removed = remove_from_denylist(db, &entries).await?;
+            for entry in &removed {
+                println!("Removed {entry}");
+            }
+            if removed.len() < entries.len() {
+                println!(
+                    "{} entries were not denylisted",
+                    entries.len() - removed.len()
+                );
+            }
+        }
+        DenylistCommands::List => {
+            for record in denylisted_repositories(db).await? {
+                match record.reason {
+                    Some(reason) => {
+                        println!("{} {} ({reason})", record.denylisted_at, record.entry)
+                    }
+                    None => println!("{} {}", record.denylisted_at, record.entry),
+                }
+            }
+        }
+    }
+    Ok(())
+}
 
-    store_in_db(db, repositories.into_iter()).await
+/// Did the error happen because every point in the crawl's [PointBudget] was spent?
+fn is_budget_exhausted(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<SearchError>(),
+        Some(SearchError::BudgetExhausted { .. })
+    )
 }
@@ -0,0 +1,287 @@
+mod clustering;
+mod diff;
+mod options;
+mod similarity;
+
+use crate::database::rustfmt_configs::{
+    all_configs, find_config_by_hash, find_config_by_repo_name,
+};
+use anyhow::Context;
+use options::{find_alias, find_option, DEFAULT_MAX_WIDTH, OPTIONS};
+use serde_json::{Map, Value};
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub use clustering::{cluster_configs, ConfigCluster};
+pub use diff::{config_diff, ConfigDiff, OptionDiff};
+pub use similarity::{find_similar, SimilarConfig, SimilarityReport};
+
+/// A parsed rustfmt configuration.
+///
+/// Deprecated option names are resolved to the options that replaced them, and values are
+/// normalized so that two configs can be compared by what rustfmt would actually do with them.
+#[derive(Debug, Clone, Default)]
+pub struct RustfmtConfig {
+    options: BTreeMap<String, Value>,
+}
+
+impl RustfmtConfig {
+    /// Parse the contents of a `rustfmt.toml` file.
+    pub fn from_toml(source: &str) -> anyhow::Result<Self> {
+        let config: Value = toml::from_str(source).context("invalid rustfmt.toml")?;
+        Self::from_json(config)
+    }
+
+    /// Read and parse a `rustfmt.toml` file from disk.
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::from_toml(&source)
+    }
+
+    /// Build a [RustfmtConfig] from the JSON representation stored in the database.
+    pub fn from_json(config: Value) -> anyhow::Result<Self> {
+        let Value::Object(table) = config else {
+            anyhow::bail!("rustfmt config must be a table of options");
+        };
+        Ok(Self::from_table(table))
+    }
+
+    fn from_table(table: Map<String, Value>) -> Self {
+        let mut options = BTreeMap::new();
+        let mut aliased = vec![];
+
+        for (name, value) in table {
+            if let Some(alias) = find_alias(&name) {
+                aliased.push((alias, value));
+                continue;
+            }
+
+            let value = normalize(&name, value);
+            options.insert(name, value);
+        }
+
+        // The new option name takes precedence when both the old and new names are set.
+        for (alias, value) in aliased {
+            if options.contains_key(alias.option) {
+                continue;
+            }
+
+            match (alias.convert)(&value) {
+                Some(value) => {
+                    options.insert(alias.option.to_string(), normalize(alias.option, value));
+                }
+                None => {
+                    options.insert(alias.alias.to_string(), value);
+                }
+            }
+        }
+
+        Self { options }
+    }
+
+    /// Options that were explicitly set in the config.
+    pub fn explicit_options(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.options
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    /// Explicitly set options whose value differs from what rustfmt would use by default.
+    pub fn non_default_options(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.explicit_options()
+            .filter(|(name, value)| self.default_value(name).as_ref() != Some(*value))
+    }
+
+    /// The value rustfmt will use for the given option, taking defaults into account.
+    ///
+    /// Returns [None] for options that rustfmt doesn't know about and that aren't set.
+    pub fn effective_value(&self, name: &str) -> Option<Value> {
+        match self.options.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.default_value(name),
+        }
+    }
+
+    /// The value rustfmt uses for the option when it isn't set.
+    /// Width heuristics depend on the `max_width` and `use_small_heuristics` of this config.
+    fn default_value(&self, name: &str) -> Option<Value> {
+        let max_width = self
+            .options
+            .get("max_width")
+            .and_then(Value::as_u64)
+            .unwrap_or(DEFAULT_MAX_WIDTH);
+        let use_small_heuristics = self
+            .options
+            .get("use_small_heuristics")
+            .and_then(Value::as_str)
+            .unwrap_or("Default");
+
+        find_option(name).map(|spec| spec.default_value(max_width, use_small_heuristics))
+    }
+
+    /// Every known rustfmt option along with any unknown options set in this config,
+    /// mapped to the value rustfmt will use.
+    pub fn effective_options(&self) -> BTreeMap<String, Value> {
+        OPTIONS
+            .iter()
+            .map(|spec| spec.name)
+            .chain(self.options.keys().map(String::as_str))
+            .filter_map(|name| Some((name.to_string(), self.effective_value(name)?)))
+            .collect()
+    }
+}
+
+/// Normalize the value of known options so that equivalent values compare equal.
+fn normalize(name: &str, value: Value) -> Value {
+    match find_option(name) {
+        Some(spec) => spec.normalize(value),
+        None => value,
+    }
+}
+
+impl FromStr for RustfmtConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_toml(s)
+    }
+}
+
+/// Where to load a rustfmt configuration from.
+#[derive(Debug, Clone)]
+pub enum ConfigSource {
+    /// A local `rustfmt.toml` file.
+    File(PathBuf),
+    /// The config stored for a repository, e.g. `rust-lang/rustfmt`.
+    Repository(String),
+    /// A stored config identified by its hash, or a unique prefix of the hash.
+    Hash(String),
+}
+
+impl FromStr for ConfigSource {
+    type Err = anyhow::Error;
+
+    /// Sources can be given explicitly with a `file:`, `repo:` or `hash:` prefix.
+    /// Otherwise existing paths are treated as files, `owner/name` as repositories,
+    /// and hex strings as config hashes.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("file:") {
+            return Ok(ConfigSource::File(PathBuf::from(path)));
+        }
+        if let Some(name) = s.strip_prefix("repo:") {
+            return Ok(ConfigSource::Repository(name.to_string()));
+        }
+        if let Some(hash) = s.strip_prefix("hash:") {
+            anyhow::ensure!(is_hex(hash), "`{hash}` is not a config hash");
+            return Ok(ConfigSource::Hash(hash.to_ascii_lowercase()));
+        }
+
+        if Path::new(s).is_file() {
+            Ok(ConfigSource::File(PathBuf::from(s)))
+        } else if s.contains('/') {
+            Ok(ConfigSource::Repository(s.to_string()))
+        } else if is_hex(s) {
+            Ok(ConfigSource::Hash(s.to_ascii_lowercase()))
+        } else {
+            anyhow::bail!("`{s}` is not a file, a repository name or a config hash")
+        }
+    }
+}
+
+/// Config hashes are hex strings, so they can be matched by prefix without escaping.
+fn is_hex(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+impl ConfigSource {
+    /// Does loading the config from this source query the database?
+    pub fn needs_database(&self) -> bool {
+        !matches!(self, ConfigSource::File(_))
+    }
+
+    /// Load the rustfmt config from this source.
+    ///
+    /// The database is only queried for repositories and config hashes, so `db` can be [None]
+    /// when loading local files.
+    pub async fn load(&self, db: Option<&PgPool>) -> anyhow::Result<RustfmtConfig> {
+        let config = match self {
+            ConfigSource::File(path) => return RustfmtConfig::from_file(path),
+            ConfigSource::Repository(name) => {
+                let db = db.context("Loading stored configs requires DATABASE_URL")?;
+                find_config_by_repo_name(db, name)
+                    .await?
+                    .with_context(|| format!("No rustfmt config stored for {name}"))?
+            }
+            ConfigSource::Hash(hash) => {
+                let db = db.context("Loading stored configs requires DATABASE_URL")?;
+                find_config_by_hash(db, hash)
+                    .await?
+                    .with_context(|| format!("No rustfmt config stored with hash {hash}"))?
+            }
+        };
+        RustfmtConfig::from_json(config)
+    }
+}
+
+/// A rustfmt config stored in the database.
+#[derive(Debug, Clone)]
+pub struct StoredConfig {
+    /// The name of the repository with the owner, e.g. `rust-lang/rustfmt`.
+    pub repo_name: String,
+    /// Path to the config file within the repository.
+    pub file_path: String,
+    pub config: RustfmtConfig,
+}
+
+impl StoredConfig {
+    /// Load every rustfmt config stored in the database.
+    ///
+    /// Configs that can't be parsed are logged and skipped.
+    pub async fn load_all(db: &PgPool) -> anyhow::Result<Vec<StoredConfig>> {
+        let configs = all_configs(db).await?;
+        Ok(configs
+            .into_iter()
+            .filter_map(
+                |(repo_name, file_path, config)| match RustfmtConfig::from_json(config) {
+                    Ok(config) => Some(StoredConfig {
+                        repo_name,
+                        file_path,
+                        config,
+                    }),
+                    Err(err) => {
+                        tracing::warn!(repo_name, file_path, invalid_config=?err);
+                        None
+                    }
+                },
+            )
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_sources() {
+        assert!(matches!(
+            "repo:rust-lang/rustfmt".parse(),
+            Ok(ConfigSource::Repository(name)) if name == "rust-lang/rustfmt"
+        ));
+        assert!(matches!(
+            "hash:ABC123".parse(),
+            Ok(ConfigSource::Hash(hash)) if hash == "abc123"
+        ));
+        assert!(matches!("abc123".parse(), Ok(ConfigSource::Hash(_))));
+    }
+
+    #[test]
+    fn config_hashes_must_be_hex() {
+        assert!("hash:".parse::<ConfigSource>().is_err());
+        assert!("hash:%".parse::<ConfigSource>().is_err());
+        assert!("hash:ab_".parse::<ConfigSource>().is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use super::similarity::{option_value_pairs, PairWeights};
+use super::StoredConfig;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Display;
+
+/// A group of stored configs that share most of their non-default options.
+#[derive(Debug, Clone)]
+pub struct ConfigCluster<'a> {
+    /// Option/value pairs set by more than half of the configs in the cluster,
+    /// ordered from most to least common.
+    defining_options: Vec<String>,
+    /// Configs in the cluster, ordered from most to least similar to the cluster's center.
+    members: Vec<&'a StoredConfig>,
+}
+
+impl<'a> ConfigCluster<'a> {
+    /// The number of configs in this cluster.
+    pub fn size(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Option/value pairs like `imports_granularity="Crate"` that define this cluster.
+    /// Empty for the cluster of configs that only use rustfmt's defaults.
+    pub fn defining_options(&self) -> &[String] {
+        &self.defining_options
+    }
+
+    /// All configs in the cluster, ordered from most to least representative.
+    pub fn members(&self) -> &[&'a StoredConfig] {
+        &self.members
+    }
+
+    /// The `n` configs that best represent the cluster.
+    pub fn representatives(&self, n: usize) -> &[&'a StoredConfig] {
+        &self.members[..std::cmp::min(n, self.members.len())]
+    }
+}
+
+impl Display for ConfigCluster<'_> {
+    /// Names the cluster by its defining options.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.defining_options.is_empty() {
+            return write!(f, "rustfmt defaults");
+        }
+        write!(f, "{}", self.defining_options.join(", "))
+    }
+}
+
+/// Group stored configs into clusters of similar option sets.
+///
+/// Configs are visited from the most to the least common set of options. Each config joins the
+/// cluster whose center it's most similar to, or starts a new cluster when it isn't at least
+/// `min_similarity` similar to any of them. Clusters are returned from largest to smallest.
+pub fn cluster_configs(corpus: &[StoredConfig], min_similarity: f64) -> Vec<ConfigCluster<'_>> {
+    let corpus_pairs = corpus
+        .iter()
+        .map(|stored| option_value_pairs(&stored.config))
+        .collect::<Vec<_>>();
+    let weights = PairWeights::new(corpus_pairs.iter());
+
+    // Configs with the exact same options always end up together, so group them up front.
+    let mut identical: HashMap<Vec<&String>, Vec<usize>> = HashMap::new();
+    for (index, pairs) in corpus_pairs.iter().enumerate() {
+        let mut key = pairs.iter().collect::<Vec<_>>();
+        key.sort();
+        identical.entry(key).or_default().push(index);
+    }
+    let mut groups = identical.into_values().collect::<Vec<_>>();
+    groups.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+
+    // Each cluster is the index of its center and (similarity, index) of each member.
+    let mut clusters: Vec<(usize, Vec<(f64, usize)>)> = vec![];
+    for group in groups {
+        let pairs = &corpus_pairs[group[0]];
+        let closest = clusters
+            .iter_mut()
+            .map(|cluster| {
+                let similarity = weights.similarity(&corpus_pairs[cluster.0], pairs);
+                (similarity, cluster)
+            })
+            .filter(|(similarity, _)| *similarity >= min_similarity)
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+
+        match closest {
+            Some((similarity, (_, members))) => {
+                members.extend(group.into_iter().map(|index| (similarity, index)));
+            }
+            None => {
+                let center = group[0];
+                clusters.push((
+                    center,
+                    group.into_iter().map(|index| (1.0, index)).collect(),
+                ));
+            }
+        }
+    }
+
+    let mut clusters = clusters
+        .into_iter()
+        .map(|(_, mut members)| {
+            members.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+            let member_pairs = members.iter().map(|(_, index)| &corpus_pairs[*index]);
+            ConfigCluster {
+                defining_options: defining_options(member_pairs, members.len()),
+                members: members.iter().map(|(_, index)| &corpus[*index]).collect(),
+            }
+        })
+        .collect::<Vec<_>>();
+    clusters.sort_by_key(|cluster| std::cmp::Reverse(cluster.size()));
+    clusters
+}
+
+/// Option/value pairs set by more than half of the cluster's members.
+fn defining_options<'a>(
+    member_pairs: impl Iterator<Item = &'a HashSet<String>>,
+    size: usize,
+) -> Vec<String> {
+    let mut counts: BTreeMap<&String, usize> = BTreeMap::new();
+    for pairs in member_pairs {
+        for pair in pairs {
+            *counts.entry(pair).or_insert(0) += 1;
+        }
+    }
+
+    let mut defining = counts
+        .into_iter()
+        .filter(|(_, count)| count * 2 > size)
+        .collect::<Vec<_>>();
+    defining.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    defining.into_iter().map(|(pair, _)| pair.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RustfmtConfig;
+
+    fn stored(repo_name: &str, source: &str) -> StoredConfig {
+        StoredConfig {
+            repo_name: repo_name.to_string(),
+            file_path: "rustfmt.toml".to_string(),
+            config: RustfmtConfig::from_toml(source).unwrap(),
+        }
+    }
+
+    fn names<'a>(cluster: &ConfigCluster<'a>) -> Vec<&'a str> {
+        cluster
+            .members()
+            .iter()
+            .map(|stored| stored.repo_name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn similar_configs_are_clustered_together() {
+        let corpus = [
+            stored("tabs/a", "hard_tabs = true"),
+            stored("tabs/b", "hard_tabs = true"),
+            stored("tabs/c", "hard_tabs = true\nmax_width = 80"),
+            stored("crate/a", "imports_granularity = \"Crate\""),
+            stored("crate/b", "imports_granularity = \"Crate\""),
+        ];
+        let clusters = cluster_configs(&corpus, 0.4);
+
+        assert_eq!(clusters.len(), 2);
+        // The most similar members come first
+        assert_eq!(names(&clusters[0]), ["tabs/a", "tabs/b", "tabs/c"]);
+        assert_eq!(clusters[0].defining_options(), ["hard_tabs=true"]);
+        assert_eq!(clusters[0].to_string(), "hard_tabs=true");
+        assert_eq!(names(&clusters[1]), ["crate/a", "crate/b"]);
+        assert_eq!(
+            clusters[1].defining_options(),
+            ["imports_granularity=\"Crate\""]
+        );
+    }
+
+    #[test]
+    fn dissimilar_configs_start_new_clusters() {
+        let corpus = [
+            stored("tabs/a", "hard_tabs = true"),
+            stored("tabs/b", "hard_tabs = true\nmax_width = 80"),
+        ];
+
+        assert_eq!(cluster_configs(&corpus, 0.4).len(), 1);
+        assert_eq!(cluster_configs(&corpus, 1.0).len(), 2);
+    }
+
+    #[test]
+    fn default_configs_form_their_own_cluster() {
+        let corpus = [
+            stored("default/a", ""),
+            stored("default/b", "max_width = 100"),
+            stored("tabs/a", "hard_tabs = true"),
+        ];
+        let clusters = cluster_configs(&corpus, 0.5);
+
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(names(&clusters[0]), ["default/a", "default/b"]);
+        assert!(clusters[0].defining_options().is_empty());
+        assert_eq!(clusters[0].to_string(), "rustfmt defaults");
+    }
+
+    #[test]
+    fn representatives_are_capped_at_the_cluster_size() {
+        let corpus = [stored("a/a", ""), stored("a/b", "")];
+        let clusters = cluster_configs(&corpus, 0.5);
+
+        assert_eq!(clusters[0].representatives(1).len(), 1);
+        assert_eq!(clusters[0].representatives(5).len(), 2);
+    }
+
+    #[test]
+    fn defining_options_are_set_by_more_than_half_the_members() {
+        let members = [
+            HashSet::from(["a=1".to_string(), "b=1".to_string()]),
+            HashSet::from(["a=1".to_string(), "b=1".to_string()]),
+            HashSet::from(["a=1".to_string(), "c=1".to_string()]),
+            HashSet::from(["c=1".to_string()]),
+        ];
+        assert_eq!(defining_options(members.iter(), members.len()), ["a=1"]);
+    }
+}
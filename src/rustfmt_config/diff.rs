@@ -0,0 +1,177 @@
+use super::RustfmtConfig;
+use serde_json::Value;
+use std::fmt::Display;
+
+/// An option whose effective value differs between two configs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptionDiff {
+    /// The name of the option
+    pub name: String,
+    /// The value in the left config. [None] if rustfmt doesn't know the option and it isn't set.
+    pub left: Option<Value>,
+    /// The value in the right config. [None] if rustfmt doesn't know the option and it isn't set.
+    pub right: Option<Value>,
+}
+
+/// The semantic differences between two rustfmt configs.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigDiff {
+    differences: Vec<OptionDiff>,
+}
+
+impl ConfigDiff {
+    /// Returns `true` if both configs format code the same way.
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    /// The options that differ, ordered by name.
+    pub fn differences(&self) -> &[OptionDiff] {
+        &self.differences
+    }
+}
+
+/// Compare the effective values of two configs.
+///
+/// Unlike a textual diff, options that are explicitly set to their default value, deprecated
+/// option names, and differently cased enum variants don't show up as differences.
+pub fn config_diff(left: &RustfmtConfig, right: &RustfmtConfig) -> ConfigDiff {
+    let left_options = left.effective_options();
+    let mut right_options = right.effective_options();
+
+    let mut differences = vec![];
+    for (name, left_value) in left_options {
+        let right_value = right_options.remove(&name);
+        if right_value.as_ref() != Some(&left_value) {
+            differences.push(OptionDiff {
+                name,
+                left: Some(left_value),
+                right: right_value,
+            });
+        }
+    }
+
+    differences.extend(
+        right_options
+            .into_iter()
+            .map(|(name, right_value)| OptionDiff {
+                name,
+                left: None,
+                right: Some(right_value),
+            }),
+    );
+    differences.sort_by(|a, b| a.name.cmp(&b.name));
+
+    ConfigDiff { differences }
+}
+
+impl Display for ConfigDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        struct OptionalValue<'a>(&'a Option<Value>);
+
+        impl Display for OptionalValue<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self.0 {
+                    Some(value) => write!(f, "{value}"),
+                    None => write!(f, "<unset>"),
+                }
+            }
+        }
+
+        for diff in &self.differences {
+            writeln!(
+                f,
+                "{}: {} -> {}",
+                diff.name,
+                OptionalValue(&diff.left),
+                OptionalValue(&diff.right)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(source: &str) -> RustfmtConfig {
+        RustfmtConfig::from_toml(source).unwrap()
+    }
+
+    #[test]
+    fn identical_configs_have_no_differences() {
+        let left = config("max_width = 80\nhard_tabs = true");
+        let right = config("hard_tabs = true\nmax_width = 80");
+        assert!(config_diff(&left, &right).is_empty());
+    }
+
+    #[test]
+    fn options_set_to_their_default_are_not_differences() {
+        let left = config("");
+        let right = config("max_width = 100\nreorder_imports = true\nedition = \"2015\"");
+        assert!(config_diff(&left, &right).is_empty());
+    }
+
+    #[test]
+    fn enum_variants_are_compared_case_insensitively() {
+        let left = config("imports_granularity = \"crate\"");
+        let right = config("imports_granularity = \"Crate\"");
+        assert!(config_diff(&left, &right).is_empty());
+    }
+
+    #[test]
+    fn deprecated_options_match_their_replacement() {
+        let left = config("merge_imports = true");
+        let right = config("imports_granularity = \"Crate\"");
+        assert!(config_diff(&left, &right).is_empty());
+    }
+
+    #[test]
+    fn differences_are_sorted_by_name() {
+        let left = config("tab_spaces = 2\nhard_tabs = true");
+        let right = config("");
+        let diff = config_diff(&left, &right);
+
+        assert_eq!(
+            diff.differences(),
+            &[
+                OptionDiff {
+                    name: "hard_tabs".to_string(),
+                    left: Some(Value::from(true)),
+                    right: Some(Value::from(false)),
+                },
+                OptionDiff {
+                    name: "tab_spaces".to_string(),
+                    left: Some(Value::from(2)),
+                    right: Some(Value::from(4)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_options_only_set_on_one_side_are_unset_on_the_other() {
+        let left = config("");
+        let right = config("not_a_rustfmt_option = 1");
+        let diff = config_diff(&left, &right);
+
+        assert_eq!(
+            diff.differences(),
+            &[OptionDiff {
+                name: "not_a_rustfmt_option".to_string(),
+                left: None,
+                right: Some(Value::from(1)),
+            }]
+        );
+        assert_eq!(diff.to_string(), "not_a_rustfmt_option: <unset> -> 1\n");
+    }
+
+    #[test]
+    fn width_heuristics_follow_max_width() {
+        // The default `fn_call_width` of 60 is scaled up along with `max_width`
+        let left = config("max_width = 120");
+        let right = config("max_width = 120\nfn_call_width = 72");
+        assert!(config_diff(&left, &right).is_empty());
+    }
+}
@@ -0,0 +1,295 @@
+use serde_json::Value;
+
+/// The `max_width` rustfmt uses when one isn't configured.
+pub(super) const DEFAULT_MAX_WIDTH: u64 = 100;
+
+/// Describes the type and default value of a rustfmt configuration option.
+pub(super) enum OptionKind {
+    Bool(bool),
+    Integer(u64),
+    /// An option that can be set to one of the listed variants. The first variant is the default.
+    /// rustfmt matches variants case-insensitively.
+    Enum(&'static [&'static str]),
+    /// An option that takes a list of values. Defaults to an empty list.
+    List,
+    /// One of the width heuristics controlled by `use_small_heuristics`.
+    /// The value is the width used when `max_width` is set to [DEFAULT_MAX_WIDTH].
+    WidthHeuristic(u64),
+}
+
+/// A rustfmt configuration option
+pub(super) struct OptionSpec {
+    pub(super) name: &'static str,
+    pub(super) kind: OptionKind,
+}
+
+/// An old option name that rustfmt still accepts in place of a newer one.
+pub(super) struct OptionAlias {
+    /// The deprecated option name
+    pub(super) alias: &'static str,
+    /// The option the alias was replaced by
+    pub(super) option: &'static str,
+    /// Converts values of the deprecated option into values of the new option.
+    pub(super) convert: fn(&Value) -> Option<Value>,
+}
+
+macro_rules! options {
+    ($($name:ident: $kind:expr,)*) => {
+        pub(super) const OPTIONS: &[OptionSpec] = &[
+            $(OptionSpec { name: stringify!($name), kind: $kind },)*
+        ];
+    };
+}
+
+use OptionKind::*;
+
+// Options and defaults are taken from rustfmt's Configurations.md
+// <https://github.com/rust-lang/rustfmt/blob/master/Configurations.md>
+options! {
+    array_width: WidthHeuristic(60),
+    attr_fn_like_width: WidthHeuristic(70),
+    binop_separator: Enum(&["Front", "Back"]),
+    blank_lines_lower_bound: Integer(0),
+    blank_lines_upper_bound: Integer(1),
+    brace_style: Enum(&["SameLineWhere", "AlwaysNextLine", "PreferSameLine"]),
+    chain_width: WidthHeuristic(60),
+    color: Enum(&["Auto", "Always", "Never"]),
+    combine_control_expr: Bool(true),
+    comment_width: Integer(80),
+    condense_wildcard_suffixes: Bool(false),
+    control_brace_style: Enum(&["AlwaysSameLine", "AlwaysNextLine", "ClosingNextLine"]),
+    disable_all_formatting: Bool(false),
+    doc_comment_code_block_width: Integer(100),
+    edition: Enum(&["2015", "2018", "2021", "2024"]),
+    empty_item_single_line: Bool(true),
+    enum_discrim_align_threshold: Integer(0),
+    error_on_line_overflow: Bool(false),
+    error_on_unformatted: Bool(false),
+    fn_call_width: WidthHeuristic(60),
+    fn_params_layout: Enum(&["Tall", "Compressed", "Vertical"]),
+    fn_single_line: Bool(false),
+    force_explicit_abi: Bool(true),
+    force_multiline_blocks: Bool(false),
+    format_code_in_doc_comments: Bool(false),
+    format_generated_files: Bool(true),
+    format_macro_bodies: Bool(true),
+    format_macro_matchers: Bool(false),
+    format_strings: Bool(false),
+    generated_marker_line_search_limit: Integer(5),
+    group_imports: Enum(&["Preserve", "StdExternalCrate", "One"]),
+    hard_tabs: Bool(false),
+    hex_literal_case: Enum(&["Preserve", "Upper", "Lower"]),
+    hide_parse_errors: Bool(false),
+    ignore: List,
+    imports_granularity: Enum(&["Preserve", "Crate", "Module", "Item", "One"]),
+    imports_indent: Enum(&["Block", "Visual"]),
+    imports_layout: Enum(&["Mixed", "Horizontal", "HorizontalVertical", "Vertical"]),
+    indent_style: Enum(&["Block", "Visual"]),
+    inline_attribute_width: Integer(0),
+    make_backup: Bool(false),
+    match_arm_blocks: Bool(true),
+    match_arm_leading_pipes: Enum(&["Never", "Always", "Preserve"]),
+    match_block_trailing_comma: Bool(false),
+    max_width: Integer(DEFAULT_MAX_WIDTH),
+    merge_derives: Bool(true),
+    newline_style: Enum(&["Auto", "Native", "Unix", "Windows"]),
+    normalize_comments: Bool(false),
+    normalize_doc_attributes: Bool(false),
+    overflow_delimited_expr: Bool(false),
+    remove_nested_parens: Bool(true),
+    reorder_impl_items: Bool(false),
+    reorder_imports: Bool(true),
+    reorder_modules: Bool(true),
+    short_array_element_width_threshold: Integer(10),
+    single_line_if_else_max_width: WidthHeuristic(50),
+    single_line_let_else_max_width: WidthHeuristic(50),
+    skip_children: Bool(false),
+    skip_macro_invocations: List,
+    space_after_colon: Bool(true),
+    space_before_colon: Bool(false),
+    spaces_around_ranges: Bool(false),
+    struct_field_align_threshold: Integer(0),
+    struct_lit_single_line: Bool(true),
+    struct_lit_width: WidthHeuristic(18),
+    struct_variant_width: WidthHeuristic(35),
+    tab_spaces: Integer(4),
+    trailing_comma: Enum(&["Vertical", "Always", "Never"]),
+    trailing_semicolon: Bool(true),
+    type_punctuation_density: Enum(&["Wide", "Compressed"]),
+    unstable_features: Bool(false),
+    use_field_init_shorthand: Bool(false),
+    use_small_heuristics: Enum(&["Default", "Off", "Max"]),
+    use_try_shorthand: Bool(false),
+    version: Enum(&["One", "Two"]),
+    where_single_line: Bool(false),
+    wrap_comments: Bool(false),
+}
+
+pub(super) const ALIASES: &[OptionAlias] = &[
+    OptionAlias {
+        alias: "fn_args_layout",
+        option: "fn_params_layout",
+        convert: |value| Some(value.clone()),
+    },
+    OptionAlias {
+        alias: "merge_imports",
+        option: "imports_granularity",
+        convert: |value| match value.as_bool()? {
+            true => Some(Value::from("Crate")),
+            false => Some(Value::from("Preserve")),
+        },
+    },
+];
+
+/// Look up the [OptionSpec] for a rustfmt configuration option.
+pub(super) fn find_option(name: &str) -> Option<&'static OptionSpec> {
+    OPTIONS.iter().find(|spec| spec.name == name)
+}
+
+/// Look up the [OptionAlias] for a deprecated rustfmt configuration option.
+pub(super) fn find_alias(alias: &str) -> Option<&'static OptionAlias> {
+    ALIASES.iter().find(|spec| spec.alias == alias)
+}
+
+impl OptionSpec {
+    /// Normalize a configured value so that equivalent values compare equal.
+    pub(super) fn normalize(&self, value: Value) -> Value {
+        match (&self.kind, value) {
+            (Enum(variants), Value::String(s)) => variants
+                .iter()
+                .find(|variant| variant.eq_ignore_ascii_case(&s))
+                .map_or(Value::String(s), |variant| Value::from(*variant)),
+            // Older versions of rustfmt accepted a bool for `use_small_heuristics`
+            (Enum(_), Value::Bool(b)) if self.name == "use_small_heuristics" => {
+                Value::from(if b { "Default" } else { "Off" })
+            }
+            (_, value) => value,
+        }
+    }
+
+    /// The value rustfmt uses for this option when it isn't explicitly configured.
+    ///
+    /// Width heuristics are derived from `max_width` and `use_small_heuristics`,
+    /// mirroring how rustfmt calculates them.
+    pub(super) fn default_value(&self, max_width: u64, use_small_heuristics: &str) -> Value {
+        match self.kind {
+            Bool(b) => Value::from(b),
+            Integer(i) => Value::from(i),
+            Enum(variants) => Value::from(variants[0]),
+            List => Value::Array(vec![]),
+            WidthHeuristic(width) => match use_small_heuristics {
+                // rustfmt ignores these heuristics when they're turned off, so the only
+                // limit left is `max_width`.
+                "Off" => match self.name {
+                    "struct_lit_width"
+                    | "struct_variant_width"
+                    | "single_line_if_else_max_width"
+                    | "single_line_let_else_max_width" => Value::from(0),
+                    _ => Value::from(max_width),
+                },
+                "Max" => Value::from(max_width),
+                _ => {
+                    let ratio = if max_width > DEFAULT_MAX_WIDTH {
+                        // round to the closest 0.1
+                        ((max_width as f64 / DEFAULT_MAX_WIDTH as f64) * 10.0).round() / 10.0
+                    } else {
+                        1.0
+                    };
+                    Value::from((width as f64 * ratio).round() as u64)
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_value(name: &str, max_width: u64, use_small_heuristics: &str) -> Value {
+        find_option(name)
+            .unwrap()
+            .default_value(max_width, use_small_heuristics)
+    }
+
+    #[test]
+    fn simple_defaults() {
+        assert_eq!(
+            default_value("hard_tabs", 100, "Default"),
+            Value::from(false)
+        );
+        assert_eq!(default_value("tab_spaces", 100, "Default"), Value::from(4));
+        assert_eq!(
+            default_value("edition", 100, "Default"),
+            Value::from("2015")
+        );
+        assert_eq!(
+            default_value("ignore", 100, "Default"),
+            Value::Array(vec![])
+        );
+    }
+
+    #[test]
+    fn width_heuristics_scale_with_max_width() {
+        assert_eq!(
+            default_value("fn_call_width", 100, "Default"),
+            Value::from(60)
+        );
+        assert_eq!(
+            default_value("fn_call_width", 120, "Default"),
+            Value::from(72)
+        );
+        // Smaller widths don't shrink the heuristics
+        assert_eq!(
+            default_value("fn_call_width", 80, "Default"),
+            Value::from(60)
+        );
+    }
+
+    #[test]
+    fn width_heuristics_with_max_and_off() {
+        assert_eq!(default_value("chain_width", 120, "Max"), Value::from(120));
+        assert_eq!(default_value("chain_width", 120, "Off"), Value::from(120));
+        assert_eq!(
+            default_value("struct_lit_width", 120, "Off"),
+            Value::from(0)
+        );
+    }
+
+    #[test]
+    fn enum_values_are_normalized_to_their_variant() {
+        let spec = find_option("imports_granularity").unwrap();
+        assert_eq!(spec.normalize(Value::from("crate")), Value::from("Crate"));
+        // Unknown variants are kept as is
+        assert_eq!(spec.normalize(Value::from("Nope")), Value::from("Nope"));
+    }
+
+    #[test]
+    fn use_small_heuristics_accepts_bools() {
+        let spec = find_option("use_small_heuristics").unwrap();
+        assert_eq!(spec.normalize(Value::from(true)), Value::from("Default"));
+        assert_eq!(spec.normalize(Value::from(false)), Value::from("Off"));
+    }
+
+    #[test]
+    fn aliases_convert_to_the_new_option() {
+        let alias = find_alias("merge_imports").unwrap();
+        assert_eq!(alias.option, "imports_granularity");
+        assert_eq!(
+            (alias.convert)(&Value::from(true)),
+            Some(Value::from("Crate"))
+        );
+        assert_eq!(
+            (alias.convert)(&Value::from(false)),
+            Some(Value::from("Preserve"))
+        );
+        assert_eq!((alias.convert)(&Value::from("yes")), None);
+
+        let alias = find_alias("fn_args_layout").unwrap();
+        assert_eq!(alias.option, "fn_params_layout");
+        assert_eq!(
+            (alias.convert)(&Value::from("Compressed")),
+            Some(Value::from("Compressed"))
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use super::{RustfmtConfig, StoredConfig};
+use std::collections::{HashMap, HashSet};
+
+/// A stored config and how similar it is to the config being compared.
+#[derive(Debug, Clone)]
+pub struct SimilarConfig<'a> {
+    /// The stored config
+    pub config: &'a StoredConfig,
+    /// Weighted Jaccard similarity between `0.0` (nothing in common) and `1.0` (equivalent)
+    pub similarity: f64,
+}
+
+/// The stored configs that are most similar to a given config.
+#[derive(Debug, Clone)]
+pub struct SimilarityReport<'a> {
+    /// The most similar configs, ordered from most to least similar.
+    pub nearest: Vec<SimilarConfig<'a>>,
+    /// The percentage of stored configs that are less unusual than the given config.
+    /// A config made up of options and values that are rarely used scores close to `100.0`.
+    pub unusualness_percentile: f64,
+}
+
+/// Option/value pairs that differ from rustfmt's defaults.
+pub(super) fn option_value_pairs(config: &RustfmtConfig) -> HashSet<String> {
+    config
+        .non_default_options()
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect()
+}
+
+/// Weights option/value pairs by how rare they are across all stored configs.
+pub(super) struct PairWeights {
+    /// The number of configs that set each option/value pair.
+    counts: HashMap<String, usize>,
+    total_configs: usize,
+}
+
+impl PairWeights {
+    pub(super) fn new<'a>(corpus: impl Iterator<Item = &'a HashSet<String>>) -> Self {
+        let mut counts = HashMap::new();
+        let mut total_configs = 0;
+        for pairs in corpus {
+            total_configs += 1;
+            for pair in pairs {
+                *counts.entry(pair.clone()).or_insert(0) += 1;
+            }
+        }
+        Self {
+            counts,
+            total_configs,
+        }
+    }
+
+    /// Inverse document frequency of the pair. Pairs that no stored config uses get the
+    /// highest weight.
+    fn weight(&self, pair: &str) -> f64 {
+        let count = self.counts.get(pair).copied().unwrap_or(0);
+        ((self.total_configs as f64 + 1.0) / (count as f64 + 1.0)).ln() + 1.0
+    }
+
+    /// How unusual a set of option/value pairs is.
+    fn rarity<'a>(&self, pairs: impl IntoIterator<Item = &'a String>) -> f64 {
+        pairs.into_iter().map(|pair| self.weight(pair)).sum()
+    }
+
+    pub(super) fn similarity(&self, left: &HashSet<String>, right: &HashSet<String>) -> f64 {
+        let union = self.rarity(left.union(right));
+        if union == 0.0 {
+            // Both configs only use the defaults
+            return 1.0;
+        }
+        let intersection = self.rarity(left.intersection(right));
+        intersection / union
+    }
+}
+
+/// Score every stored config by its weighted Jaccard similarity to `config`, where each
+/// non-default option/value pair is weighted by how rarely it's used, and return the `limit`
+/// closest matches.
+pub fn find_similar<'a>(
+    config: &RustfmtConfig,
+    corpus: &'a [StoredConfig],
+    limit: usize,
+) -> SimilarityReport<'a> {
+    let target = option_value_pairs(config);
+    let corpus_pairs = corpus
+        .iter()
+        .map(|stored| option_value_pairs(&stored.config))
+        .collect::<Vec<_>>();
+    let weights = PairWeights::new(corpus_pairs.iter());
+
+    let mut nearest = corpus
+        .iter()
+        .zip(&corpus_pairs)
+        .map(|(stored, pairs)| SimilarConfig {
+            config: stored,
+            similarity: weights.similarity(&target, pairs),
+        })
+        .collect::<Vec<_>>();
+    nearest.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    nearest.truncate(limit);
+
+    let target_rarity = weights.rarity(&target);
+    let less_unusual = corpus_pairs
+        .iter()
+        .filter(|pairs| weights.rarity(*pairs) < target_rarity)
+        .count();
+    let unusualness_percentile = if corpus.is_empty() {
+        0.0
+    } else {
+        less_unusual as f64 / corpus.len() as f64 * 100.0
+    };
+
+    SimilarityReport {
+        nearest,
+        unusualness_percentile,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(pairs: &[&str]) -> HashSet<String> {
+        pairs.iter().map(|pair| pair.to_string()).collect()
+    }
+
+    fn stored(repo_name: &str, source: &str) -> StoredConfig {
+        StoredConfig {
+            repo_name: repo_name.to_string(),
+            file_path: "rustfmt.toml".to_string(),
+            config: RustfmtConfig::from_toml(source).unwrap(),
+        }
+    }
+
+    #[test]
+    fn option_value_pairs_skip_defaults() {
+        let config = RustfmtConfig::from_toml("max_width = 100\nhard_tabs = true").unwrap();
+        assert_eq!(option_value_pairs(&config), pairs(&["hard_tabs=true"]));
+    }
+
+    #[test]
+    fn rare_pairs_weigh_more() {
+        let corpus = [pairs(&["a=1", "b=1"]), pairs(&["a=1"]), pairs(&["a=1"])];
+        let weights = PairWeights::new(corpus.iter());
+        assert!(weights.weight("b=1") > weights.weight("a=1"));
+        // Pairs no config uses weigh the most
+        assert!(weights.weight("c=1") > weights.weight("b=1"));
+    }
+
+    #[test]
+    fn weighted_jaccard_similarity() {
+        let corpus = [pairs(&["a=1", "b=1"]), pairs(&["a=1"]), pairs(&["a=1"])];
+        let weights = PairWeights::new(corpus.iter());
+
+        assert_eq!(weights.similarity(&corpus[0], &corpus[0]), 1.0);
+        assert_eq!(weights.similarity(&pairs(&["a=1"]), &pairs(&["c=1"])), 0.0);
+        // Configs that only use the defaults are equivalent
+        assert_eq!(weights.similarity(&pairs(&[]), &pairs(&[])), 1.0);
+
+        let expected = weights.weight("a=1") / (weights.weight("a=1") + weights.weight("b=1"));
+        assert!((weights.similarity(&corpus[0], &corpus[1]) - expected).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn nearest_configs_are_ordered_by_similarity() {
+        let corpus = [
+            stored("a/different", "tab_spaces = 2"),
+            stored("a/same", "hard_tabs = true\nmax_width = 80"),
+            stored("a/close", "hard_tabs = true"),
+        ];
+        let config = RustfmtConfig::from_toml("hard_tabs = true\nmax_width = 80").unwrap();
+        let report = find_similar(&config, &corpus, 2);
+
+        let names = report
+            .nearest
+            .iter()
+            .map(|similar| similar.config.repo_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a/same", "a/close"]);
+        assert_eq!(report.nearest[0].similarity, 1.0);
+    }
+
+    #[test]
+    fn unusualness_percentile() {
+        let corpus = [
+            stored("a/default", ""),
+            stored("a/common", "hard_tabs = true"),
+            stored("a/also-common", "hard_tabs = true"),
+            stored("a/rare", "hard_tabs = true\ntab_spaces = 2"),
+        ];
+
+        // The defaults are the least unusual config there is
+        let report = find_similar(&RustfmtConfig::default(), &corpus, 1);
+        assert_eq!(report.unusualness_percentile, 0.0);
+
+        // Only the config using the defaults is less unusual
+        let config = RustfmtConfig::from_toml("hard_tabs = true").unwrap();
+        let report = find_similar(&config, &corpus, 1);
+        assert_eq!(report.unusualness_percentile, 25.0);
+
+        // An option no stored config uses is more unusual than every stored config
+        let config = RustfmtConfig::from_toml("hard_tabs = true\ntab_spaces = 8").unwrap();
+        let report = find_similar(&config, &corpus, 1);
+        assert_eq!(report.unusualness_percentile, 100.0);
+
+        let report = find_similar(&config, &[], 1);
+        assert_eq!(report.unusualness_percentile, 0.0);
+    }
+}